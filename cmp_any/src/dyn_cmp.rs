@@ -0,0 +1,277 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+//! Macros to implement comparison traits for `dyn Trait`.
+//!
+//! Each macro defines a helper trait, implemented for every suitable type, which the user
+//! trait should have as a supertrait. The comparison traits are implemented for the given
+//! `dyn` types, and the standard library implementations then make `Box<dyn Trait>`,
+//! `Rc<dyn Trait>` and `Arc<dyn Trait>` comparable too.
+//!
+//! ```
+//! use std::collections::BTreeSet;
+//! use std::collections::HashSet;
+//! use std::sync::Arc;
+//!
+//! cmp_any::dyn_eq!(trait KeyEq: Eq for dyn Key);
+//! cmp_any::dyn_ord!(trait KeyOrd for dyn Key);
+//! cmp_any::dyn_hash!(trait KeyHash for dyn Key);
+//!
+//! trait Key: KeyEq + KeyOrd + KeyHash {}
+//!
+//! impl Key for i32 {}
+//! impl Key for String {}
+//!
+//! let keys: Vec<Arc<dyn Key>> = vec![Arc::new(1), Arc::new("x".to_owned()), Arc::new(1)];
+//! assert_eq!(keys.iter().collect::<HashSet<_>>().len(), 2);
+//! assert_eq!(keys.iter().collect::<BTreeSet<_>>().len(), 2);
+//! ```
+
+/// Define a helper trait with an `eq_token` method returning [`PartialEqAny`](crate::PartialEqAny),
+/// and implement [`PartialEq`] for the given `dyn` types using it.
+///
+/// Written `trait Helper: PartialEq for dyn Trait`, the helper is implemented for all
+/// `PartialEq` types. Written `trait Helper: Eq for dyn Trait`, it is implemented for all `Eq`
+/// types, and [`Eq`] is also implemented for the `dyn` types.
+///
+/// ```
+/// cmp_any::dyn_eq!(pub trait ShapeEq: PartialEq for dyn Shape);
+///
+/// pub trait Shape: ShapeEq {}
+///
+/// #[derive(PartialEq)]
+/// struct Circle(f64);
+/// impl Shape for Circle {}
+///
+/// #[derive(PartialEq)]
+/// struct Square(f64);
+/// impl Shape for Square {}
+///
+/// let a: Box<dyn Shape> = Box::new(Circle(1.0));
+/// assert!(a == Box::new(Circle(1.0)) as Box<dyn Shape>);
+/// assert!(a != Box::new(Square(1.0)) as Box<dyn Shape>);
+/// ```
+#[macro_export]
+macro_rules! dyn_eq {
+    ($(#[$attr:meta])* $vis:vis trait $helper:ident: PartialEq for $($t:ty),+ $(,)?) => {
+        $crate::dyn_eq!(@helper [$(#[$attr])*] [$vis] $helper [PartialEq] $($t),+);
+    };
+    ($(#[$attr:meta])* $vis:vis trait $helper:ident: Eq for $($t:ty),+ $(,)?) => {
+        $crate::dyn_eq!(@helper [$(#[$attr])*] [$vis] $helper [Eq] $($t),+);
+        $(
+            impl ::core::cmp::Eq for $t {}
+        )+
+    };
+    (@helper [$($attr:tt)*] [$vis:vis] $helper:ident [$bound:ident] $($t:ty),+) => {
+        $($attr)*
+        $vis trait $helper {
+            fn eq_token(&self) -> $crate::PartialEqAny<'_>;
+        }
+
+        impl<T: ::core::cmp::$bound + 'static> $helper for T {
+            fn eq_token(&self) -> $crate::PartialEqAny<'_> {
+                $crate::PartialEqAny::new(self)
+            }
+        }
+
+        $(
+            impl ::core::cmp::PartialEq for $t {
+                fn eq(&self, other: &Self) -> bool {
+                    ::core::cmp::PartialEq::eq(&$helper::eq_token(self), &$helper::eq_token(other))
+                }
+            }
+        )+
+    };
+}
+
+/// Define a helper trait with an `ord_token` method returning [`OrdAny`](crate::OrdAny),
+/// implemented for all `Ord` types, and implement [`PartialOrd`] and [`Ord`] for the given
+/// `dyn` types using it.
+///
+/// Values of different types are ordered by their [`TypeId`](std::any::TypeId). As [`Ord`]
+/// requires [`Eq`], the `dyn` types should also use [`dyn_eq!`](crate::dyn_eq) with `Eq`.
+///
+/// ```
+/// cmp_any::dyn_eq!(trait NameEq: Eq for dyn Name);
+/// cmp_any::dyn_ord!(trait NameOrd for dyn Name);
+///
+/// trait Name: NameEq + NameOrd {}
+/// impl Name for &'static str {}
+///
+/// let mut names: Vec<Box<dyn Name>> = vec![Box::new("b"), Box::new("a")];
+/// names.sort();
+/// assert!(names[0] == Box::new("a") as Box<dyn Name>);
+/// ```
+#[macro_export]
+macro_rules! dyn_ord {
+    ($(#[$attr:meta])* $vis:vis trait $helper:ident for $($t:ty),+ $(,)?) => {
+        $(#[$attr])*
+        $vis trait $helper {
+            fn ord_token(&self) -> $crate::OrdAny<'_>;
+        }
+
+        impl<T: ::core::cmp::Ord + 'static> $helper for T {
+            fn ord_token(&self) -> $crate::OrdAny<'_> {
+                $crate::OrdAny::new(self)
+            }
+        }
+
+        $(
+            impl ::core::cmp::PartialOrd for $t {
+                fn partial_cmp(&self, other: &Self) -> ::core::option::Option<::core::cmp::Ordering> {
+                    ::core::option::Option::Some(::core::cmp::Ord::cmp(self, other))
+                }
+            }
+
+            impl ::core::cmp::Ord for $t {
+                fn cmp(&self, other: &Self) -> ::core::cmp::Ordering {
+                    ::core::cmp::Ord::cmp(&$helper::ord_token(self), &$helper::ord_token(other))
+                }
+            }
+        )+
+    };
+}
+
+/// Define a helper trait with a `hash_token` method returning [`HashAny`](crate::HashAny),
+/// implemented for all `Hash + Eq` types, and implement [`Hash`](std::hash::Hash) for the given
+/// `dyn` types using it.
+///
+/// To be used as a map key, the `dyn` types should also use [`dyn_eq!`](crate::dyn_eq)
+/// with `Eq`, which is consistent with the hash.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use std::rc::Rc;
+///
+/// cmp_any::dyn_eq!(trait IdEq: Eq for dyn Id);
+/// cmp_any::dyn_hash!(trait IdHash for dyn Id);
+///
+/// trait Id: IdEq + IdHash {}
+/// impl Id for u32 {}
+/// impl Id for char {}
+///
+/// let mut map: HashMap<Rc<dyn Id>, &str> = HashMap::new();
+/// map.insert(Rc::new(1u32), "one");
+/// map.insert(Rc::new('1'), "char");
+/// assert_eq!(map[&(Rc::new(1u32) as Rc<dyn Id>)], "one");
+/// ```
+#[macro_export]
+macro_rules! dyn_hash {
+    ($(#[$attr:meta])* $vis:vis trait $helper:ident for $($t:ty),+ $(,)?) => {
+        $(#[$attr])*
+        $vis trait $helper {
+            fn hash_token(&self) -> $crate::HashAny<'_>;
+        }
+
+        impl<T: ::core::hash::Hash + ::core::cmp::Eq + 'static> $helper for T {
+            fn hash_token(&self) -> $crate::HashAny<'_> {
+                $crate::HashAny::new(self)
+            }
+        }
+
+        $(
+            impl ::core::hash::Hash for $t {
+                fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+                    ::core::hash::Hash::hash(&$helper::hash_token(self), state)
+                }
+            }
+        )+
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+    use std::collections::BTreeSet;
+    use std::collections::HashMap;
+    use std::fmt::Debug;
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    crate::dyn_eq!(trait ValueEq: Eq for dyn Value, dyn Value + Send + Sync);
+    crate::dyn_ord!(trait ValueOrd for dyn Value, dyn Value + Send + Sync);
+    crate::dyn_hash!(trait ValueHash for dyn Value, dyn Value + Send + Sync);
+
+    trait Value: ValueEq + ValueOrd + ValueHash + Debug {}
+
+    impl Value for i32 {}
+    impl Value for String {}
+
+    #[test]
+    fn test_dyn_value() {
+        let x: Box<dyn Value> = Box::new(1);
+        assert!(x == Box::new(1) as Box<dyn Value>);
+        assert!(x != Box::new(2) as Box<dyn Value>);
+        assert!(x != Box::new("1".to_owned()) as Box<dyn Value>);
+        assert_eq!(
+            x.cmp(&(Box::new(2) as Box<dyn Value>)),
+            Ordering::Less,
+            "values of the same type are compared by value"
+        );
+
+        let xs: BTreeSet<Rc<dyn Value>> = [Rc::new(2) as Rc<dyn Value>, Rc::new(1), Rc::new(2)]
+            .into_iter()
+            .collect();
+        assert_eq!(format!("{xs:?}"), "{1, 2}");
+
+        let mut map: HashMap<Arc<dyn Value + Send + Sync>, i32> = HashMap::new();
+        map.insert(Arc::new(1), 10);
+        map.insert(Arc::new("1".to_owned()), 20);
+        map.insert(Arc::new(1), 30);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map[&(Arc::new(1) as Arc<dyn Value + Send + Sync>)], 30);
+    }
+
+    crate::dyn_eq!(
+        /// Only `PartialEq`, so floats are allowed.
+        pub(crate) trait FloatEq: PartialEq for dyn Float
+    );
+
+    trait Float: FloatEq {}
+
+    impl Float for f64 {}
+
+    #[test]
+    fn test_dyn_partial_eq() {
+        let x: &dyn Float = &1.0;
+        assert!(x == &1.0 as &dyn Float);
+        let nan: &dyn Float = &f64::NAN;
+        assert!(nan != nan);
+    }
+
+    // The generated code doesn't depend on the names in scope.
+    mod shadowed {
+        #![allow(dead_code)]
+
+        mod std {}
+        trait PartialEq {}
+        trait Eq {}
+        trait PartialOrd {}
+        trait Ord {}
+        trait Hash {}
+        struct Option;
+
+        crate::dyn_eq!(pub(crate) trait KeyEq: Eq for dyn Key);
+        crate::dyn_ord!(pub(crate) trait KeyOrd for dyn Key);
+        crate::dyn_hash!(pub(crate) trait KeyHash for dyn Key);
+
+        pub(crate) trait Key: KeyEq + KeyOrd + KeyHash {}
+
+        impl Key for i32 {}
+    }
+
+    #[test]
+    fn test_dyn_shadowed() {
+        let x: &dyn shadowed::Key = &1;
+        assert!(x == &1 as &dyn shadowed::Key);
+        assert!(x < &2 as &dyn shadowed::Key);
+        assert_eq!([x].into_iter().collect::<BTreeSet<_>>().len(), 1);
+    }
+}
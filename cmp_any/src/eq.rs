@@ -10,33 +10,99 @@
 
 use std::any::Any;
 use std::any::TypeId;
+use std::error::Error;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Display;
 use std::marker::PhantomData;
 
+use crate::erased::DebugFn;
+use crate::erased::ErasedRef;
+use crate::erased::debug_fn;
+use crate::erased::fmt_token;
+
+/// Returned when comparing tokens for values of different types, with the names of the types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeMismatch {
+    pub left: &'static str,
+    pub right: &'static str,
+}
+
+impl Display for TypeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Compared values of different types: `{}` and `{}`",
+            self.left, self.right
+        )
+    }
+}
+
+impl Error for TypeMismatch {}
+
 /// A comparable "token" that can be returned to wrap a reference to an [`Any`
 /// type](Any) for [`PartialEq`](PartialEq).
 ///
 /// This lets dyn traits be comparable by having all implementations return some
 /// "token" that can be considered [`PartialEq`](PartialEq).
+#[derive(Clone, Copy)]
 pub struct PartialEqAny<'a> {
-    type_id: TypeId,
-    cmp: unsafe fn(*const (), *const ()) -> bool,
-    val: *const (),
-    _marker: PhantomData<&'a dyn Any>,
+    // Fields are visible to the crate so `AnyKey` can create tokens from its parts.
+    pub(crate) type_id: TypeId,
+    pub(crate) type_name: &'static str,
+    pub(crate) cmp: unsafe fn(ErasedRef, ErasedRef) -> bool,
+    pub(crate) val: ErasedRef,
+    pub(crate) debug: Option<DebugFn>,
+    pub(crate) _marker: PhantomData<&'a dyn Any>,
 }
 
 impl<'a> PartialEqAny<'a> {
+    /// Create a token for the value. The value may be unsized, such as a `str` or slice.
+    ///
+    /// ```
+    /// use cmp_any::PartialEqAny;
+    ///
+    /// assert!(PartialEqAny::new("literal") == PartialEqAny::new("literal"));
+    /// assert!(PartialEqAny::new(&[1, 2][..]) != PartialEqAny::new(&[1, 2, 3][..]));
+    /// ```
+    #[inline]
+    pub fn new<A: PartialEq + ?Sized + 'static>(a: &'a A) -> Self {
+        Self::with_debug(a, None)
+    }
+
+    /// Like [`new`](PartialEqAny::new), but the [`Debug`] output of the token includes the value.
+    ///
+    /// ```
+    /// use cmp_any::PartialEqAny;
+    ///
+    /// assert_eq!(
+    ///     format!("{:?}", PartialEqAny::new_debug(&1)),
+    ///     "PartialEqAny { type: \"i32\", value: 1 }"
+    /// );
+    /// assert_eq!(
+    ///     format!("{:?}", PartialEqAny::new(&1)),
+    ///     "PartialEqAny { type: \"i32\", .. }"
+    /// );
+    /// ```
     #[inline]
-    pub fn new<A: PartialEq + 'static>(a: &'a A) -> Self {
+    pub fn new_debug<A: PartialEq + Debug + ?Sized + 'static>(a: &'a A) -> Self {
+        Self::with_debug(a, Some(debug_fn::<A>()))
+    }
+
+    #[inline]
+    fn with_debug<A: PartialEq + ?Sized + 'static>(a: &'a A, debug: Option<DebugFn>) -> Self {
         PartialEqAny {
             type_id: TypeId::of::<A>(),
+            type_name: std::any::type_name::<A>(),
             cmp: |this, other| {
                 // SAFETY: We only call `cmp` with
                 //   `this.type_id == other.type_id == TypeId::of::<A>()`.
-                let this = unsafe { &*(this as *const A) };
-                let other = unsafe { &*(other as *const A) };
+                let this = unsafe { this.get::<A>() };
+                let other = unsafe { other.get::<A>() };
                 this == other
             },
-            val: a as *const A as *const (),
+            val: ErasedRef::new(a),
+            debug,
             _marker: PhantomData,
         }
     }
@@ -47,6 +113,64 @@ impl<'a> PartialEqAny<'a> {
         self.type_id
     }
 
+    /// Get the name of the referenced type.
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Get the referenced value, if it is of type `T`.
+    #[inline]
+    pub fn downcast_ref<T: ?Sized + 'static>(&self) -> Option<&'a T> {
+        if self.type_id == TypeId::of::<T>() {
+            // SAFETY: The value is of type `T`.
+            Some(unsafe { self.val.get::<T>() })
+        } else {
+            None
+        }
+    }
+
+    /// Like `==`, but returns an error if the values have different types.
+    ///
+    /// ```
+    /// use cmp_any::PartialEqAny;
+    /// use cmp_any::TypeMismatch;
+    ///
+    /// assert_eq!(
+    ///     PartialEqAny::new(&1).try_eq(&PartialEqAny::new(&2)),
+    ///     Ok(false)
+    /// );
+    /// assert_eq!(
+    ///     PartialEqAny::new(&1).try_eq(&PartialEqAny::new("1")),
+    ///     Err(TypeMismatch {
+    ///         left: "i32",
+    ///         right: "str"
+    ///     })
+    /// );
+    /// ```
+    #[inline]
+    pub fn try_eq(&self, other: &PartialEqAny<'a>) -> Result<bool, TypeMismatch> {
+        if self.type_id == other.type_id {
+            Ok(unsafe { (self.cmp)(self.val, other.val) })
+        } else {
+            Err(TypeMismatch {
+                left: self.type_name,
+                right: other.type_name,
+            })
+        }
+    }
+
+    /// The referenced value, whose type is identified by `type_id`.
+    #[inline]
+    pub(crate) fn val(&self) -> ErasedRef {
+        self.val
+    }
+
+    /// Debug format the token as the token type `name`.
+    pub(crate) fn fmt_as(&self, f: &mut fmt::Formatter<'_>, name: &str) -> fmt::Result {
+        fmt_token(f, name, self.type_name, self.val, self.debug)
+    }
+
     /// gets an instance that always compares to false
     #[inline]
     pub fn always_false() -> Self {
@@ -65,7 +189,13 @@ impl<'a> PartialEqAny<'a> {
 impl<'a> PartialEq for PartialEqAny<'a> {
     #[inline]
     fn eq(&self, other: &PartialEqAny<'a>) -> bool {
-        self.type_id == other.type_id && unsafe { (self.cmp)(self.val, other.val) }
+        self.try_eq(other).unwrap_or(false)
+    }
+}
+
+impl Debug for PartialEqAny<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_as(f, "PartialEqAny")
     }
 }
 
@@ -73,6 +203,7 @@ impl<'a> PartialEq for PartialEqAny<'a> {
 #[allow(clippy::bool_assert_comparison)]
 mod tests {
     use crate::eq::PartialEqAny;
+    use crate::eq::TypeMismatch;
 
     struct Wrap<T>(T);
 
@@ -110,4 +241,35 @@ mod tests {
         assert!(f != f);
         assert!(f != w.token());
     }
+
+    #[test]
+    fn test_introspection() {
+        let x = PartialEqAny::new_debug("x");
+        assert_eq!(x.type_name(), "str");
+        assert_eq!(x.downcast_ref::<str>(), Some("x"));
+        assert_eq!(x.downcast_ref::<String>(), None);
+        assert_eq!(
+            format!("{x:?}"),
+            "PartialEqAny { type: \"str\", value: \"x\" }"
+        );
+        assert_eq!(
+            x.try_eq(&PartialEqAny::new(&1u8)),
+            Err(TypeMismatch {
+                left: "str",
+                right: "u8"
+            })
+        );
+        assert_eq!(x.try_eq(&PartialEqAny::new("x")), Ok(true));
+    }
+
+    #[test]
+    fn test_unsized() {
+        assert!(PartialEqAny::new("foo") == PartialEqAny::new("foo"));
+        assert!(PartialEqAny::new("foo") != PartialEqAny::new("bar"));
+        // `str` and `&str` are different types.
+        assert!(PartialEqAny::new("foo") != PartialEqAny::new(&"foo"));
+        let xs = [1, 2, 3];
+        assert!(PartialEqAny::new(&xs[..2]) == PartialEqAny::new(&[1, 2][..]));
+        assert!(PartialEqAny::new(&xs[..]) != PartialEqAny::new(&xs[1..]));
+    }
 }
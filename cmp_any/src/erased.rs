@@ -0,0 +1,88 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+use std::fmt;
+use std::fmt::Debug;
+use std::mem;
+use std::mem::MaybeUninit;
+
+/// A reference to a possibly unsized value with its type erased, without allocation.
+///
+/// Stored as pointer words (rather than `usize`) so the pointer keeps its provenance.
+#[derive(Clone, Copy)]
+pub(crate) struct ErasedRef {
+    ptr: MaybeUninit<[*const (); 2]>,
+}
+
+impl ErasedRef {
+    #[inline]
+    pub(crate) fn new<A: ?Sized>(a: &A) -> Self {
+        const {
+            // Thin pointers are one word, and pointers to slices, `str` and `dyn` are two.
+            assert!(mem::size_of::<*const A>() <= mem::size_of::<[*const (); 2]>());
+        }
+        let mut ptr = MaybeUninit::<[*const (); 2]>::uninit();
+        // SAFETY: The storage is large enough (checked above) and sufficiently aligned,
+        //   as pointer words are at least as aligned as any pointer.
+        unsafe { ptr.as_mut_ptr().cast::<*const A>().write(a) };
+        ErasedRef { ptr }
+    }
+
+    /// A reference to a sized value at `ptr`, for callers which have already erased its type.
+    #[inline]
+    pub(crate) fn from_ptr(ptr: *const ()) -> Self {
+        let mut words = MaybeUninit::<[*const (); 2]>::uninit();
+        // SAFETY: A thin pointer is the first word, as written by `new` for sized types.
+        unsafe { words.as_mut_ptr().cast::<*const ()>().write(ptr) };
+        ErasedRef { ptr: words }
+    }
+
+    /// Get the reference back.
+    ///
+    /// # Safety
+    ///
+    /// Must be called with the same `A` as `new`, and the result must not outlive the
+    /// original reference.
+    #[inline]
+    pub(crate) unsafe fn get<'a, A: ?Sized>(self) -> &'a A {
+        unsafe { &*self.ptr.as_ptr().cast::<*const A>().read() }
+    }
+}
+
+/// Debug print the value of an `ErasedRef`, which must refer to an `A`.
+pub(crate) type DebugFn = unsafe fn(ErasedRef, &mut fmt::Formatter<'_>) -> fmt::Result;
+
+pub(crate) fn debug_fn<A: Debug + ?Sized>() -> DebugFn {
+    |val, f| Debug::fmt(unsafe { val.get::<A>() }, f)
+}
+
+/// Debug print a token, including the value if it was created with a `DebugFn`.
+pub(crate) fn fmt_token(
+    f: &mut fmt::Formatter<'_>,
+    name: &str,
+    type_name: &str,
+    val: ErasedRef,
+    debug: Option<DebugFn>,
+) -> fmt::Result {
+    struct Value(ErasedRef, DebugFn);
+
+    impl Debug for Value {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            unsafe { (self.1)(self.0, f) }
+        }
+    }
+
+    let mut s = f.debug_struct(name);
+    s.field("type", &type_name);
+    match debug {
+        Some(debug) => s.field("value", &Value(val, debug)).finish(),
+        None => s.finish_non_exhaustive(),
+    }
+}
@@ -0,0 +1,166 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+use std::any::TypeId;
+use std::fmt;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::hash::Hasher;
+
+use crate::PartialEqAny;
+use crate::TypeMismatch;
+use crate::erased::ErasedRef;
+
+/// A hashable "token" that can be returned to wrap a reference to an [`Any`
+/// type](std::any::Any) for [`Hash`](Hash) and [`Eq`](Eq).
+///
+/// This lets dyn traits be used as `HashMap` keys by having all implementations return
+/// some "token". Equality is defined by the wrapped [`PartialEqAny`], so equal tokens always
+/// have equal hashes.
+#[derive(Clone, Copy)]
+pub struct HashAny<'a> {
+    // Fields are visible to the crate so `AnyKey` can create tokens from its parts.
+    pub(crate) eq: PartialEqAny<'a>,
+    pub(crate) hash: unsafe fn(ErasedRef, &mut dyn Hasher),
+}
+
+impl<'a> HashAny<'a> {
+    #[inline]
+    pub fn new<A: Hash + Eq + ?Sized + 'static>(a: &'a A) -> Self {
+        Self::with_eq::<A>(PartialEqAny::new(a))
+    }
+
+    /// Like [`new`](HashAny::new), but the [`Debug`] output of the token includes the value.
+    #[inline]
+    pub fn new_debug<A: Hash + Eq + Debug + ?Sized + 'static>(a: &'a A) -> Self {
+        Self::with_eq::<A>(PartialEqAny::new_debug(a))
+    }
+
+    /// The hash function for `A`, which must be the type of the value of `eq`.
+    #[inline]
+    fn with_eq<A: Hash + ?Sized + 'static>(eq: PartialEqAny<'a>) -> Self {
+        HashAny {
+            eq,
+            hash: |this, mut state| {
+                // SAFETY: We only call `hash` with the value of `eq`, which is an `A`.
+                let this = unsafe { this.get::<A>() };
+                this.hash(&mut state)
+            },
+        }
+    }
+
+    /// Get `TypeId` of the referenced type.
+    #[inline]
+    pub fn type_id(&self) -> TypeId {
+        self.eq.type_id()
+    }
+
+    /// Get the name of the referenced type.
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        self.eq.type_name()
+    }
+
+    /// Get the referenced value, if it is of type `T`.
+    #[inline]
+    pub fn downcast_ref<T: ?Sized + 'static>(&self) -> Option<&'a T> {
+        self.eq.downcast_ref()
+    }
+
+    /// Like `==`, but returns an error if the values have different types.
+    #[inline]
+    pub fn try_eq(&self, other: &HashAny<'a>) -> Result<bool, TypeMismatch> {
+        self.eq.try_eq(&other.eq)
+    }
+
+    /// The equality token this token is consistent with.
+    #[inline]
+    pub fn eq_token(&self) -> PartialEqAny<'a> {
+        self.eq
+    }
+}
+
+impl<'a> PartialEq for HashAny<'a> {
+    #[inline]
+    fn eq(&self, other: &HashAny<'a>) -> bool {
+        self.eq == other.eq
+    }
+}
+
+// `HashAny::new` requires `Eq` of the wrapped type.
+impl Eq for HashAny<'_> {}
+
+impl Debug for HashAny<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.eq.fmt_as(f, "HashAny")
+    }
+}
+
+/// Hash the type id first, then the value.
+impl Hash for HashAny<'_> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.type_id().hash(state);
+        unsafe { (self.hash)(self.eq.val(), state) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::Hash;
+    use std::hash::Hasher;
+
+    use crate::HashAny;
+
+    fn hash(x: HashAny) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        x.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_hash_any() {
+        assert_eq!(hash(HashAny::new(&1)), hash(HashAny::new(&1)));
+        assert!(HashAny::new(&1) == HashAny::new(&1));
+        assert!(HashAny::new(&1) != HashAny::new(&2));
+        // Same value, different types.
+        assert!(HashAny::new(&1i32) != HashAny::new(&1u32));
+        assert_ne!(hash(HashAny::new(&1i32)), hash(HashAny::new(&1u32)));
+        assert!(HashAny::new(&"x").eq_token() == HashAny::new(&"x").eq_token());
+    }
+
+    #[test]
+    fn test_hash_map() {
+        let (a, b, c) = (1, "b".to_owned(), 1u8);
+        let mut map = HashMap::new();
+        map.insert(HashAny::new(&a), "a");
+        map.insert(HashAny::new(&b), "b");
+        map.insert(HashAny::new(&c), "c");
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get(&HashAny::new(&1)), Some(&"a"));
+        assert_eq!(map.get(&HashAny::new(&"b".to_owned())), Some(&"b"));
+        assert_eq!(map.get(&HashAny::new(&1u8)), Some(&"c"));
+        assert_eq!(map.get(&HashAny::new(&2u8)), None);
+    }
+
+    #[test]
+    fn test_hash_any_introspection() {
+        let x = HashAny::new_debug(&1u8);
+        assert_eq!(x.downcast_ref::<u8>(), Some(&1));
+        assert_eq!(format!("{x:?}"), "HashAny { type: \"u8\", value: 1 }");
+        assert_eq!(
+            format!("{:?}", HashAny::new(&1u8)),
+            "HashAny { type: \"u8\", .. }"
+        );
+        assert!(x.try_eq(&HashAny::new(&1i8)).is_err());
+    }
+}
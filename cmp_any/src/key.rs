@@ -0,0 +1,331 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+use std::any::TypeId;
+use std::cmp::Ordering;
+use std::fmt;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::marker::PhantomData;
+use std::mem;
+use std::mem::MaybeUninit;
+use std::sync::Arc;
+
+use dupe::Dupe;
+
+use crate::HashAny;
+use crate::OrdAny;
+use crate::PartialEqAny;
+use crate::erased::DebugFn;
+use crate::erased::ErasedRef;
+
+/// Either a small `Copy` value, or the pointer of an `Arc`.
+type Storage = MaybeUninit<[*const (); 2]>;
+
+/// How to clone and drop a value stored in an `Arc`, given its pointer.
+#[derive(Clone, Copy)]
+struct ArcFns {
+    clone: unsafe fn(*const ()),
+    drop: unsafe fn(*const ()),
+}
+
+/// An owned value of an arbitrary type, which can be compared, hashed and debug printed.
+///
+/// Values of different types are never equal, and are ordered by [`TypeId`], like [`OrdAny`].
+/// Like the tokens, the key stores the [`TypeId`] and the type erased functions to compare,
+/// hash and print the value next to it, rather than going through a trait object.
+///
+/// Values are stored in an [`Arc`], except small [`Copy`] values created with
+/// [`new_copy`](AnyKey::new_copy), which are stored inline without allocation. Either way
+/// `AnyKey` is [`Dupe`], which makes it suitable as the key of a map holding values keyed
+/// by different types:
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// use cmp_any::AnyKey;
+///
+/// #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+/// struct GetUser(u32);
+/// #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+/// struct GetGroup(u32);
+///
+/// let mut cache = HashMap::new();
+/// cache.insert(AnyKey::new(GetUser(1)), "alice");
+/// cache.insert(AnyKey::new_copy(GetGroup(1)), "admins");
+/// assert_eq!(cache[&AnyKey::new(GetUser(1))], "alice");
+/// assert_eq!(cache[&AnyKey::new_copy(GetGroup(1))], "admins");
+/// ```
+pub struct AnyKey {
+    storage: Storage,
+    // `None` if the value is stored inline.
+    arc: Option<ArcFns>,
+    type_id: TypeId,
+    type_name: &'static str,
+    eq: unsafe fn(ErasedRef, ErasedRef) -> bool,
+    cmp: unsafe fn(ErasedRef, ErasedRef) -> Ordering,
+    hash: unsafe fn(ErasedRef, &mut dyn Hasher),
+    debug: DebugFn,
+}
+
+// The stored value is `Send + Sync`.
+unsafe impl Send for AnyKey {}
+unsafe impl Sync for AnyKey {}
+
+impl AnyKey {
+    pub fn new<A: Ord + Hash + Debug + Send + Sync + 'static>(value: A) -> Self {
+        let ptr = Arc::into_raw(Arc::new(value));
+        let mut storage = Storage::uninit();
+        unsafe { storage.as_mut_ptr().cast::<*const A>().write(ptr) };
+        let arc = ArcFns {
+            // SAFETY: Only called with the pointer of an `Arc<A>` which is still alive.
+            clone: |ptr| unsafe { Arc::increment_strong_count(ptr.cast::<A>()) },
+            drop: |ptr| unsafe { Arc::decrement_strong_count(ptr.cast::<A>()) },
+        };
+        Self::with_storage(unsafe { &*ptr }, storage, Some(arc))
+    }
+
+    /// Like [`new`](AnyKey::new), but values which fit in two pointers are stored inline,
+    /// without allocation.
+    pub fn new_copy<A: Ord + Hash + Debug + Copy + Send + Sync + 'static>(value: A) -> Self {
+        if mem::size_of::<A>() > mem::size_of::<Storage>()
+            || mem::align_of::<A>() > mem::align_of::<Storage>()
+        {
+            return Self::new(value);
+        }
+        let mut storage = Storage::uninit();
+        // SAFETY: The value fits in the storage, which is sufficiently aligned (checked above).
+        unsafe { storage.as_mut_ptr().cast::<A>().write(value) };
+        Self::with_storage(&value, storage, None)
+    }
+
+    /// A key storing an `A`, taking the functions for `A` from tokens for `value`.
+    fn with_storage<A: Ord + Hash + Debug + 'static>(
+        value: &A,
+        storage: Storage,
+        arc: Option<ArcFns>,
+    ) -> Self {
+        let ord = OrdAny::new_debug(value);
+        let hash = HashAny::new_debug(value);
+        AnyKey {
+            storage,
+            arc,
+            type_id: ord.type_id,
+            type_name: ord.type_name,
+            eq: hash.eq.cmp,
+            cmp: ord.cmp,
+            hash: hash.hash,
+            debug: ord.debug.expect("created with `new_debug`"),
+        }
+    }
+
+    /// The address of the stored value.
+    #[inline]
+    fn ptr(&self) -> *const () {
+        match self.arc {
+            // SAFETY: The first word of the storage is the pointer of the `Arc`.
+            Some(_) => unsafe { self.storage.as_ptr().cast::<*const ()>().read() },
+            None => self.storage.as_ptr().cast(),
+        }
+    }
+
+    #[inline]
+    fn val(&self) -> ErasedRef {
+        ErasedRef::from_ptr(self.ptr())
+    }
+
+    /// Get `TypeId` of the stored type.
+    #[inline]
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    /// A token to compare the stored value with other values.
+    #[inline]
+    pub fn ord_token(&self) -> OrdAny<'_> {
+        OrdAny {
+            type_id: self.type_id,
+            type_name: self.type_name,
+            type_key: None,
+            cmp: self.cmp,
+            val: self.val(),
+            debug: Some(self.debug),
+            _marker: PhantomData,
+        }
+    }
+
+    /// A token to hash the stored value.
+    #[inline]
+    pub fn hash_token(&self) -> HashAny<'_> {
+        HashAny {
+            eq: PartialEqAny {
+                type_id: self.type_id,
+                type_name: self.type_name,
+                cmp: self.eq,
+                val: self.val(),
+                debug: Some(self.debug),
+                _marker: PhantomData,
+            },
+            hash: self.hash,
+        }
+    }
+
+    /// Get the stored value, if it is of type `A`.
+    #[inline]
+    pub fn downcast_ref<A: 'static>(&self) -> Option<&A> {
+        if self.type_id == TypeId::of::<A>() {
+            // SAFETY: The value is of type `A`.
+            Some(unsafe { self.val().get::<A>() })
+        } else {
+            None
+        }
+    }
+}
+
+impl Clone for AnyKey {
+    #[inline]
+    fn clone(&self) -> Self {
+        if let Some(arc) = self.arc {
+            unsafe { (arc.clone)(self.ptr()) };
+        }
+        AnyKey {
+            storage: self.storage,
+            arc: self.arc,
+            type_id: self.type_id,
+            type_name: self.type_name,
+            eq: self.eq,
+            cmp: self.cmp,
+            hash: self.hash,
+            debug: self.debug,
+        }
+    }
+}
+
+impl Dupe for AnyKey {}
+
+impl Drop for AnyKey {
+    #[inline]
+    fn drop(&mut self) {
+        if let Some(arc) = self.arc {
+            unsafe { (arc.drop)(self.ptr()) };
+        }
+    }
+}
+
+impl PartialEq for AnyKey {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.type_id == other.type_id && unsafe { (self.eq)(self.val(), other.val()) }
+    }
+}
+
+impl Eq for AnyKey {}
+
+impl PartialOrd for AnyKey {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Compare by type id first, then by value.
+impl Ord for AnyKey {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.ord_token().cmp(&other.ord_token())
+    }
+}
+
+/// Hash the type id first, then the value.
+impl Hash for AnyKey {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hash_token().hash(state)
+    }
+}
+
+impl Debug for AnyKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        unsafe { (self.debug)(self.val(), f) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+    use std::collections::BTreeSet;
+    use std::collections::HashMap;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::Hash;
+    use std::hash::Hasher;
+    use std::sync::Arc;
+
+    use dupe::Dupe;
+
+    use crate::AnyKey;
+
+    fn hash(x: &AnyKey) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        x.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_any_key() {
+        let mut map = HashMap::new();
+        map.insert(AnyKey::new(1), "i32");
+        map.insert(AnyKey::new(1u8), "u8");
+        map.insert(AnyKey::new("1".to_owned()), "string");
+        assert_eq!(map.len(), 3);
+        assert_eq!(map[&AnyKey::new(1u8)], "u8");
+        assert_eq!(map.get(&AnyKey::new(2u8)), None);
+
+        let set: BTreeSet<_> = [AnyKey::new(3), AnyKey::new(1), AnyKey::new(3)]
+            .into_iter()
+            .collect();
+        assert_eq!(format!("{set:?}"), "{1, 3}");
+    }
+
+    #[test]
+    fn test_any_key_dupe() {
+        let value = Arc::new("shared".to_owned());
+        let x = AnyKey::new(value.dupe());
+        let y = x.dupe();
+        assert_eq!(x, y);
+        assert_eq!(y.downcast_ref::<Arc<String>>(), Some(&value));
+        assert_eq!(y.downcast_ref::<String>(), None);
+        assert_eq!(Arc::strong_count(&value), 2);
+        drop(x);
+        assert_eq!(Arc::strong_count(&value), 2);
+        drop(y);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn test_any_key_inline() {
+        let x = AnyKey::new_copy(7u32);
+        assert!(x.arc.is_none());
+        assert_eq!(x, AnyKey::new(7u32));
+        assert_eq!(x.cmp(&AnyKey::new(8u32)), Ordering::Less);
+        assert_ne!(x, AnyKey::new_copy(7u64));
+        assert_eq!(hash(&x), hash(&AnyKey::new(7u32)));
+        assert_eq!(x.dupe().downcast_ref::<u32>(), Some(&7));
+        assert_eq!(format!("{x:?}"), "7");
+
+        // Too large, or too aligned, to store inline.
+        let y = AnyKey::new_copy([1u64; 3]);
+        assert!(y.arc.is_some());
+        assert_eq!(y.downcast_ref::<[u64; 3]>(), Some(&[1; 3]));
+        let z = AnyKey::new_copy(1u128);
+        assert!(z.arc.is_some());
+        assert_eq!(z, AnyKey::new(1u128));
+    }
+}
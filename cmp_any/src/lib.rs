@@ -8,8 +8,18 @@
  * above-listed licenses.
  */
 
+mod dyn_cmp;
 mod eq;
+mod erased;
+mod hash;
+mod key;
 mod ord;
+mod partial_ord;
 
 pub use eq::PartialEqAny;
+pub use eq::TypeMismatch;
+pub use hash::HashAny;
+pub use key::AnyKey;
 pub use ord::OrdAny;
+pub use ord::StableTypeKey;
+pub use partial_ord::PartialOrdAny;
@@ -11,29 +11,119 @@
 use std::any::Any;
 use std::any::TypeId;
 use std::cmp::Ordering;
+use std::fmt;
+use std::fmt::Debug;
 use std::marker::PhantomData;
 
+use crate::TypeMismatch;
+use crate::erased::DebugFn;
+use crate::erased::ErasedRef;
+use crate::erased::debug_fn;
+use crate::erased::fmt_token;
+
+/// A name for a type which is stable across builds, used to order values of different types
+/// deterministically with [`OrdAny::new_stable`].
+///
+/// Keys should be unique, as values of types with the same key are ordered by [`TypeId`].
+pub trait StableTypeKey {
+    const KEY: &'static str;
+}
+
 /// Ordering between arbitrary types.
+///
+/// By default values of different types are ordered by [`TypeId`], which is arbitrary and may
+/// differ between builds. Tokens created with [`new_stable`](OrdAny::new_stable) or
+/// [`new_by_type_name`](OrdAny::new_by_type_name) are instead ordered by a type key,
+/// which makes sorted heterogeneous collections deterministic.
+///
+/// Tokens created with a type key are ordered before tokens without one, and the tokens for
+/// a type should always be created the same way, as tokens of the same type created in different
+/// ways are ordered by how they were created rather than by value.
 pub struct OrdAny<'a> {
-    type_id: TypeId,
-    cmp: unsafe fn(*const (), *const ()) -> Ordering,
-    val: *const (),
-    _marker: PhantomData<&'a dyn Any>,
+    // Fields are visible to the crate for conversions to and from `PartialOrdAny`, and so
+    // `AnyKey` can create tokens from its parts.
+    pub(crate) type_id: TypeId,
+    pub(crate) type_name: &'static str,
+    pub(crate) type_key: Option<&'static str>,
+    pub(crate) cmp: unsafe fn(ErasedRef, ErasedRef) -> Ordering,
+    pub(crate) val: ErasedRef,
+    pub(crate) debug: Option<DebugFn>,
+    pub(crate) _marker: PhantomData<&'a dyn Any>,
 }
 
 impl<'a> OrdAny<'a> {
     #[inline]
-    pub fn new<A: Ord + 'static>(a: &'a A) -> Self {
+    pub fn new<A: Ord + ?Sized + 'static>(a: &'a A) -> Self {
+        Self::with_type_key(a, None, None)
+    }
+
+    /// Like [`new`](OrdAny::new), but the [`Debug`] output of the token includes the value.
+    #[inline]
+    pub fn new_debug<A: Ord + Debug + ?Sized + 'static>(a: &'a A) -> Self {
+        Self::with_type_key(a, None, Some(debug_fn::<A>()))
+    }
+
+    /// Like [`new`](OrdAny::new), but values of different types are ordered by
+    /// [`StableTypeKey::KEY`].
+    ///
+    /// ```
+    /// use cmp_any::OrdAny;
+    /// use cmp_any::StableTypeKey;
+    ///
+    /// #[derive(PartialEq, Eq, PartialOrd, Ord)]
+    /// struct Apple(u32);
+    /// impl StableTypeKey for Apple {
+    ///     const KEY: &'static str = "fruit.apple";
+    /// }
+    ///
+    /// #[derive(PartialEq, Eq, PartialOrd, Ord)]
+    /// struct Banana;
+    /// impl StableTypeKey for Banana {
+    ///     const KEY: &'static str = "fruit.banana";
+    /// }
+    ///
+    /// assert!(OrdAny::new_stable(&Apple(100)) < OrdAny::new_stable(&Banana));
+    /// ```
+    #[inline]
+    pub fn new_stable<A: Ord + StableTypeKey + ?Sized + 'static>(a: &'a A) -> Self {
+        Self::with_type_key(a, Some(A::KEY), None)
+    }
+
+    /// Like [`new`](OrdAny::new), but values of different types are ordered by
+    /// [`type_name`](std::any::type_name).
+    ///
+    /// Type names are not guaranteed to be unique, or to be the same between compiler versions,
+    /// but are stable between builds with the same compiler.
+    ///
+    /// ```
+    /// use cmp_any::OrdAny;
+    ///
+    /// assert!(OrdAny::new_by_type_name(&10i64) < OrdAny::new_by_type_name(&1u8));
+    /// ```
+    #[inline]
+    pub fn new_by_type_name<A: Ord + ?Sized + 'static>(a: &'a A) -> Self {
+        Self::with_type_key(a, Some(std::any::type_name::<A>()), None)
+    }
+
+    #[inline]
+    fn with_type_key<A: Ord + ?Sized + 'static>(
+        a: &'a A,
+        type_key: Option<&'static str>,
+        debug: Option<DebugFn>,
+    ) -> Self {
         OrdAny {
             type_id: TypeId::of::<A>(),
+            type_name: std::any::type_name::<A>(),
+            type_key,
             cmp: |this, other| {
                 // SAFETY: We only call `cmp` with
                 //   `this.type_id == other.type_id == TypeId::of::<A>()`.
-                let this = unsafe { &*(this as *const A) };
-                let other = unsafe { &*(other as *const A) };
+                let this = unsafe { this.get::<A>() };
+                let other = unsafe { other.get::<A>() };
                 this.cmp(other)
             },
-            val: a as *const A as *const (),
+            val: ErasedRef::new(a),
+            debug,
             _marker: PhantomData,
         }
     }
@@ -43,6 +133,42 @@ impl<'a> OrdAny<'a> {
     pub fn type_id(&self) -> TypeId {
         self.type_id
     }
+
+    /// Get the name of the referenced type.
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Get the referenced value, if it is of type `T`.
+    #[inline]
+    pub fn downcast_ref<T: ?Sized + 'static>(&self) -> Option<&'a T> {
+        if self.type_id == TypeId::of::<T>() {
+            // SAFETY: The value is of type `T`.
+            Some(unsafe { self.val.get::<T>() })
+        } else {
+            None
+        }
+    }
+
+    /// Like `cmp`, but returns an error if the values have different types.
+    #[inline]
+    pub fn try_cmp(&self, other: &OrdAny<'a>) -> Result<Ordering, TypeMismatch> {
+        if self.type_id == other.type_id {
+            Ok(unsafe { (self.cmp)(self.val, other.val) })
+        } else {
+            Err(TypeMismatch {
+                left: self.type_name,
+                right: other.type_name,
+            })
+        }
+    }
+
+    /// Like `==`, but returns an error if the values have different types.
+    #[inline]
+    pub fn try_eq(&self, other: &OrdAny<'a>) -> Result<bool, TypeMismatch> {
+        Ok(self.try_cmp(other)? == Ordering::Equal)
+    }
 }
 
 impl<'a> PartialEq for OrdAny<'a> {
@@ -54,6 +180,12 @@ impl<'a> PartialEq for OrdAny<'a> {
 
 impl Eq for OrdAny<'_> {}
 
+impl Debug for OrdAny<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_token(f, "OrdAny", self.type_name, self.val, self.debug)
+    }
+}
+
 impl<'a> PartialOrd for OrdAny<'a> {
     #[inline]
     fn partial_cmp(&self, other: &OrdAny<'a>) -> Option<Ordering> {
@@ -61,10 +193,19 @@ impl<'a> PartialOrd for OrdAny<'a> {
     }
 }
 
-/// Compare by type id first, then by value.
+/// Compare by type key first (if any), then by type id, then by value.
 impl Ord for OrdAny<'_> {
     #[inline]
     fn cmp(&self, other: &Self) -> Ordering {
+        let key_cmp = match (self.type_key, other.type_key) {
+            (Some(x), Some(y)) => x.cmp(y),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+        if key_cmp != Ordering::Equal {
+            return key_cmp;
+        }
         let type_cmp = self.type_id.cmp(&other.type_id);
         if type_cmp != Ordering::Equal {
             return type_cmp;
@@ -79,6 +220,8 @@ mod tests {
     use std::cmp::Ordering;
 
     use crate::OrdAny;
+    use crate::StableTypeKey;
+    use crate::TypeMismatch;
 
     #[test]
     fn test_ord_any() {
@@ -93,4 +236,64 @@ mod tests {
             TypeId::of::<i32>() < TypeId::of::<bool>()
         );
     }
+
+    #[test]
+    fn test_ord_any_introspection() {
+        let x = OrdAny::new_debug(&1u8);
+        assert_eq!(x.type_name(), "u8");
+        assert_eq!(x.downcast_ref::<u8>(), Some(&1));
+        assert_eq!(x.downcast_ref::<i8>(), None);
+        assert_eq!(format!("{x:?}"), "OrdAny { type: \"u8\", value: 1 }");
+        assert_eq!(x.try_cmp(&OrdAny::new(&2u8)), Ok(Ordering::Less));
+        assert_eq!(
+            x.try_eq(&OrdAny::new("1")),
+            Err(TypeMismatch {
+                left: "u8",
+                right: "str"
+            })
+        );
+    }
+
+    #[test]
+    fn test_ord_any_unsized() {
+        assert!(OrdAny::new("a") < OrdAny::new("b"));
+        assert!(OrdAny::new(&[1, 2][..]) < OrdAny::new(&[1, 2, 3][..]));
+        assert!(OrdAny::new_by_type_name("x") == OrdAny::new_by_type_name("x"));
+    }
+
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
+    struct A(i32);
+
+    impl StableTypeKey for A {
+        const KEY: &'static str = "a";
+    }
+
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
+    struct B(i32);
+
+    impl StableTypeKey for B {
+        const KEY: &'static str = "b";
+    }
+
+    #[test]
+    fn test_ord_any_stable() {
+        assert!(OrdAny::new_stable(&A(2)) < OrdAny::new_stable(&B(1)));
+        assert!(OrdAny::new_stable(&B(1)) > OrdAny::new_stable(&A(2)));
+        assert!(OrdAny::new_stable(&A(1)) < OrdAny::new_stable(&A(2)));
+        assert!(OrdAny::new_stable(&A(1)) == OrdAny::new_stable(&A(1)));
+        assert!(OrdAny::new_by_type_name(&true) < OrdAny::new_by_type_name(&0i32));
+        // Keyed tokens come before unkeyed ones.
+        assert!(OrdAny::new_stable(&B(1)) < OrdAny::new(&A(0)));
+        assert!(OrdAny::new(&A(0)) > OrdAny::new_by_type_name(&B(1)));
+
+        let mut xs = [
+            OrdAny::new_stable(&B(1)),
+            OrdAny::new_stable(&A(3)),
+            OrdAny::new_stable(&A(1)),
+        ];
+        xs.sort();
+        assert!(xs[0] == OrdAny::new_stable(&A(1)));
+        assert!(xs[1] == OrdAny::new_stable(&A(3)));
+        assert!(xs[2] == OrdAny::new_stable(&B(1)));
+    }
 }
@@ -0,0 +1,245 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+use std::any::Any;
+use std::any::TypeId;
+use std::cmp::Ordering;
+use std::fmt;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+use crate::OrdAny;
+use crate::TypeMismatch;
+use crate::erased::DebugFn;
+use crate::erased::ErasedRef;
+use crate::erased::debug_fn;
+use crate::erased::fmt_token;
+
+/// How values of the same type are compared.
+#[derive(Clone, Copy)]
+enum Cmp {
+    Partial(unsafe fn(ErasedRef, ErasedRef) -> Option<Ordering>),
+    /// The token was converted from an [`OrdAny`], so can be converted back.
+    Total {
+        cmp: unsafe fn(ErasedRef, ErasedRef) -> Ordering,
+        type_key: Option<&'static str>,
+    },
+}
+
+/// Partial ordering between arbitrary types.
+///
+/// Like [`OrdAny`], but only requires [`PartialOrd`], so can wrap types such as `f64`.
+/// Values of different types are incomparable, as are values the type itself considers
+/// incomparable, such as NaN.
+///
+/// ```
+/// use std::cmp::Ordering;
+///
+/// use cmp_any::PartialOrdAny;
+///
+/// assert_eq!(
+///     PartialOrdAny::new(&1.0).partial_cmp(&PartialOrdAny::new(&2.0)),
+///     Some(Ordering::Less)
+/// );
+/// assert_eq!(
+///     PartialOrdAny::new(&1.0).partial_cmp(&PartialOrdAny::new(&f64::NAN)),
+///     None
+/// );
+/// assert_eq!(
+///     PartialOrdAny::new(&1.0).partial_cmp(&PartialOrdAny::new(&1)),
+///     None
+/// );
+/// ```
+///
+/// Tokens converted from an [`OrdAny`] can be converted back with [`TryFrom`].
+pub struct PartialOrdAny<'a> {
+    type_id: TypeId,
+    type_name: &'static str,
+    cmp: Cmp,
+    val: ErasedRef,
+    debug: Option<DebugFn>,
+    _marker: PhantomData<&'a dyn Any>,
+}
+
+impl<'a> PartialOrdAny<'a> {
+    #[inline]
+    pub fn new<A: PartialOrd + ?Sized + 'static>(a: &'a A) -> Self {
+        Self::with_debug(a, None)
+    }
+
+    /// Like [`new`](PartialOrdAny::new), but the [`Debug`] output of the token includes the value.
+    #[inline]
+    pub fn new_debug<A: PartialOrd + Debug + ?Sized + 'static>(a: &'a A) -> Self {
+        Self::with_debug(a, Some(debug_fn::<A>()))
+    }
+
+    #[inline]
+    fn with_debug<A: PartialOrd + ?Sized + 'static>(a: &'a A, debug: Option<DebugFn>) -> Self {
+        PartialOrdAny {
+            type_id: TypeId::of::<A>(),
+            type_name: std::any::type_name::<A>(),
+            cmp: Cmp::Partial(|this, other| {
+                // SAFETY: We only call `cmp` with
+                //   `this.type_id == other.type_id == TypeId::of::<A>()`.
+                let this = unsafe { this.get::<A>() };
+                let other = unsafe { other.get::<A>() };
+                this.partial_cmp(other)
+            }),
+            val: ErasedRef::new(a),
+            debug,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Get `TypeId` of the referenced type.
+    #[inline]
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    /// Get the name of the referenced type.
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Get the referenced value, if it is of type `T`.
+    #[inline]
+    pub fn downcast_ref<T: ?Sized + 'static>(&self) -> Option<&'a T> {
+        if self.type_id == TypeId::of::<T>() {
+            // SAFETY: The value is of type `T`.
+            Some(unsafe { self.val.get::<T>() })
+        } else {
+            None
+        }
+    }
+
+    /// Like `partial_cmp`, but returns an error if the values have different types,
+    /// so `None` only means the values are incomparable.
+    #[inline]
+    pub fn try_partial_cmp(
+        &self,
+        other: &PartialOrdAny<'a>,
+    ) -> Result<Option<Ordering>, TypeMismatch> {
+        if self.type_id != other.type_id {
+            return Err(TypeMismatch {
+                left: self.type_name,
+                right: other.type_name,
+            });
+        }
+        // Both tokens refer to the same type, so either comparison function will do.
+        Ok(match self.cmp {
+            Cmp::Partial(cmp) => unsafe { cmp(self.val, other.val) },
+            Cmp::Total { cmp, .. } => Some(unsafe { cmp(self.val, other.val) }),
+        })
+    }
+}
+
+impl<'a> From<OrdAny<'a>> for PartialOrdAny<'a> {
+    #[inline]
+    fn from(x: OrdAny<'a>) -> Self {
+        PartialOrdAny {
+            type_id: x.type_id,
+            type_name: x.type_name,
+            cmp: Cmp::Total {
+                cmp: x.cmp,
+                type_key: x.type_key,
+            },
+            val: x.val,
+            debug: x.debug,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Succeeds if the token was converted from an [`OrdAny`], otherwise returns the token back.
+impl<'a> TryFrom<PartialOrdAny<'a>> for OrdAny<'a> {
+    type Error = PartialOrdAny<'a>;
+
+    #[inline]
+    fn try_from(x: PartialOrdAny<'a>) -> Result<Self, Self::Error> {
+        match x.cmp {
+            Cmp::Partial(_) => Err(x),
+            Cmp::Total { cmp, type_key } => Ok(OrdAny {
+                type_id: x.type_id,
+                type_name: x.type_name,
+                type_key,
+                cmp,
+                val: x.val,
+                debug: x.debug,
+                _marker: PhantomData,
+            }),
+        }
+    }
+}
+
+impl<'a> PartialEq for PartialOrdAny<'a> {
+    #[inline]
+    fn eq(&self, other: &PartialOrdAny<'a>) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+/// Values of different types are incomparable.
+impl<'a> PartialOrd for PartialOrdAny<'a> {
+    #[inline]
+    fn partial_cmp(&self, other: &PartialOrdAny<'a>) -> Option<Ordering> {
+        self.try_partial_cmp(other).ok().flatten()
+    }
+}
+
+impl Debug for PartialOrdAny<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_token(f, "PartialOrdAny", self.type_name, self.val, self.debug)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use crate::OrdAny;
+    use crate::PartialOrdAny;
+    use crate::TypeMismatch;
+
+    #[test]
+    fn test_partial_ord_any() {
+        assert!(PartialOrdAny::new(&1.0) < PartialOrdAny::new(&2.0));
+        assert!(PartialOrdAny::new(&1.0) == PartialOrdAny::new(&1.0));
+        assert!(PartialOrdAny::new(&f64::NAN) != PartialOrdAny::new(&f64::NAN));
+        assert!(PartialOrdAny::new(&1.0f64) != PartialOrdAny::new(&1.0f32));
+        assert_eq!(
+            PartialOrdAny::new(&f64::NAN).try_partial_cmp(&PartialOrdAny::new(&1.0)),
+            Ok(None)
+        );
+        assert_eq!(
+            PartialOrdAny::new(&1.0f64).try_partial_cmp(&PartialOrdAny::new(&1.0f32)),
+            Err(TypeMismatch {
+                left: "f64",
+                right: "f32"
+            })
+        );
+        assert_eq!(
+            PartialOrdAny::new_debug("x").downcast_ref::<str>(),
+            Some("x")
+        );
+    }
+
+    #[test]
+    fn test_partial_ord_any_from_ord_any() {
+        let x = PartialOrdAny::from(OrdAny::new_by_type_name(&1));
+        assert_eq!(x.partial_cmp(&PartialOrdAny::new(&2)), Some(Ordering::Less));
+        assert_eq!(x.partial_cmp(&PartialOrdAny::new(&1u8)), None);
+
+        let x = OrdAny::try_from(x).unwrap();
+        assert!(x < OrdAny::new_by_type_name(&1u8));
+        assert!(OrdAny::try_from(PartialOrdAny::new(&1)).is_err());
+    }
+}
@@ -0,0 +1,216 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+//! Forwarding the flags of a [`Formatter`](fmt::Formatter) to values written elsewhere.
+//!
+//! There is no stable way to construct a `Formatter`, so the flags are forwarded by picking the
+//! matching format string. Width and precision can be passed as arguments, but the fill
+//! character cannot, so padding with a fill other than space is done by hand.
+
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Write;
+
+use crate::layout;
+
+/// Expand to a `write!` call with the format string matching the given flags.
+///
+/// Each flag named after the `[]` is dispatched on in turn, building up the format spec.
+macro_rules! write_flags {
+    ($out:ident, $v:ident, $flags:ident, [$($spec:literal)*] align $($rest:ident)*) => {
+        match $flags.align {
+            None => write_flags!($out, $v, $flags, [$($spec)*] $($rest)*),
+            Some(fmt::Alignment::Left) => write_flags!($out, $v, $flags, [$($spec)* "<"] $($rest)*),
+            Some(fmt::Alignment::Center) => write_flags!($out, $v, $flags, [$($spec)* "^"] $($rest)*),
+            Some(fmt::Alignment::Right) => write_flags!($out, $v, $flags, [$($spec)* ">"] $($rest)*),
+        }
+    };
+    ($out:ident, $v:ident, $flags:ident, [$($spec:literal)*] sign_plus $($rest:ident)*) => {
+        if $flags.sign_plus {
+            write_flags!($out, $v, $flags, [$($spec)* "+"] $($rest)*)
+        } else {
+            write_flags!($out, $v, $flags, [$($spec)*] $($rest)*)
+        }
+    };
+    ($out:ident, $v:ident, $flags:ident, [$($spec:literal)*] alternate $($rest:ident)*) => {
+        if $flags.alternate {
+            write_flags!($out, $v, $flags, [$($spec)* "#"] $($rest)*)
+        } else {
+            write_flags!($out, $v, $flags, [$($spec)*] $($rest)*)
+        }
+    };
+    ($out:ident, $v:ident, $flags:ident, [$($spec:literal)*] zero_pad $($rest:ident)*) => {
+        if $flags.zero_pad {
+            write_flags!($out, $v, $flags, [$($spec)* "0"] $($rest)*)
+        } else {
+            write_flags!($out, $v, $flags, [$($spec)*] $($rest)*)
+        }
+    };
+    ($out:ident, $v:ident, $flags:ident, [$($spec:literal)*]) => {
+        match ($flags.width, $flags.precision) {
+            (None, None) => write!($out, concat!("{:", $($spec,)* "}"), $v),
+            (Some(w), None) => write!($out, concat!("{:", $($spec,)* "w$}"), $v, w = w),
+            (None, Some(p)) => write!($out, concat!("{:", $($spec,)* ".p$}"), $v, p = p),
+            (Some(w), Some(p)) => {
+                write!($out, concat!("{:", $($spec,)* "w$.p$}"), $v, w = w, p = p)
+            }
+        }
+    };
+}
+
+/// The flags of a [`Formatter`](fmt::Formatter) which affect how values are displayed.
+#[derive(Clone, Copy)]
+pub(crate) struct Flags {
+    pub(crate) fill: char,
+    pub(crate) align: Option<fmt::Alignment>,
+    pub(crate) width: Option<usize>,
+    pub(crate) precision: Option<usize>,
+    pub(crate) sign_plus: bool,
+    pub(crate) alternate: bool,
+    pub(crate) zero_pad: bool,
+}
+
+impl Flags {
+    pub(crate) fn of(f: &fmt::Formatter) -> Self {
+        Flags {
+            fill: f.fill(),
+            align: f.align(),
+            width: f.width(),
+            precision: f.precision(),
+            sign_plus: f.sign_plus(),
+            alternate: f.alternate(),
+            zero_pad: f.sign_aware_zero_pad(),
+        }
+    }
+
+    /// The same flags, without any padding.
+    pub(crate) fn unpadded(self) -> Self {
+        Flags {
+            fill: ' ',
+            align: None,
+            width: None,
+            zero_pad: false,
+            ..self
+        }
+    }
+
+    /// Write `v` to `out`, as if by `Display::fmt` with a formatter with these flags.
+    pub(crate) fn write(self, out: &mut dyn Write, v: &dyn Display) -> fmt::Result {
+        match (self.align, self.width) {
+            (Some(align), Some(width)) if self.fill != ' ' => {
+                let unpadded = self.unpadded();
+                pad(out, self.fill, align, width, |w| unpadded.write(w, v))
+            }
+            _ => write_flags!(out, v, self, [] align sign_plus alternate zero_pad),
+        }
+    }
+}
+
+/// Write the output of `write` padded to `width` characters with `fill`.
+///
+/// The output isn't buffered, so writing stops wherever `out` stops it, such as at the limit of
+/// [`display_with_limits`](crate::display_with_limits). To pad before the output, it is first
+/// written to count up to `width` characters.
+pub(crate) fn pad(
+    out: &mut dyn Write,
+    fill: char,
+    align: fmt::Alignment,
+    width: usize,
+    write: impl Fn(&mut dyn Write) -> fmt::Result,
+) -> fmt::Result {
+    let before = match align {
+        fmt::Alignment::Left => 0,
+        fmt::Alignment::Center => width.saturating_sub(measure(width, &write)?) / 2,
+        fmt::Alignment::Right => width.saturating_sub(measure(width, &write)?),
+    };
+    for _ in 0..before {
+        out.write_char(fill)?;
+    }
+    let mut counted = Count {
+        inner: &mut *out,
+        len: 0,
+    };
+    write(&mut counted)?;
+    for _ in before + counted.len..width {
+        out.write_char(fill)?;
+    }
+    Ok(())
+}
+
+/// The number of characters written by `write`, or `max` if there are at least `max`.
+fn measure(max: usize, write: impl Fn(&mut dyn Write) -> fmt::Result) -> Result<usize, fmt::Error> {
+    // Writing stops after `max` characters.
+    struct Measure {
+        len: usize,
+        max: usize,
+    }
+
+    impl Write for Measure {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.len += s.chars().count();
+            if self.len >= self.max {
+                Err(fmt::Error)
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    // The output is only counted, so mustn't move the column.
+    let _saved = layout::save();
+    let mut measure = Measure { len: 0, max };
+    match write(&mut measure) {
+        Ok(()) => Ok(measure.len),
+        Err(_) if measure.len >= max => Ok(max),
+        Err(e) => Err(e),
+    }
+}
+
+/// Counts the characters written through it.
+struct Count<W> {
+    inner: W,
+    len: usize,
+}
+
+impl<W: Write> Write for Count<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.len += s.chars().count();
+        self.inner.write_str(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use std::fmt::Display;
+
+    use crate::flags::Flags;
+
+    /// Displays the value through `Flags`, to check it matches displaying it directly.
+    struct Forward<T>(T);
+
+    impl<T: Display> Display for Forward<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            Flags::of(f).write(f, &self.0)
+        }
+    }
+
+    #[test]
+    fn test_flags() {
+        assert_eq!(format!("{:}", Forward(1.5)), format!("{:}", 1.5));
+        assert_eq!(format!("{:+.3}", Forward(1.5)), format!("{:+.3}", 1.5));
+        assert_eq!(format!("{:08.2}", Forward(-1.5)), format!("{:08.2}", -1.5));
+        assert_eq!(format!("{:^7}", Forward("ab")), format!("{:^7}", "ab"));
+        assert_eq!(format!("{:6}", Forward(12)), format!("{:6}", 12));
+        assert_eq!(format!("{:6}", Forward("x")), format!("{:6}", "x"));
+        assert_eq!(format!("{:*^7}", Forward("ab")), format!("{:*^7}", "ab"));
+        assert_eq!(format!("{:->+6}", Forward(3)), format!("{:->+6}", 3));
+    }
+}
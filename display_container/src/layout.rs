@@ -0,0 +1,238 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+//! State for laying out containers to a line width, shared with nested containers.
+//!
+//! Nested containers are displayed through [`Display`](std::fmt::Display), so the state is kept
+//! in a thread-local, and restored when each container or item is done.
+
+use std::cell::Cell;
+use std::fmt;
+use std::fmt::Write;
+
+#[derive(Clone, Copy)]
+pub(crate) struct Layout {
+    /// The maximum line width, if the container is laid out to one. Otherwise the column is
+    /// only tracked for nested containers which are.
+    pub(crate) width: Option<usize>,
+    /// The column the next output will be written at.
+    pub(crate) column: usize,
+    /// The column the lines of the current item start at.
+    indent: usize,
+    /// The number of items being written, used to find the innermost item.
+    depth: usize,
+    /// Whether containers are being written on one line, to check they fit.
+    pub(crate) flat: bool,
+}
+
+thread_local! {
+    static LAYOUT: Cell<Option<Layout>> = const { Cell::new(None) };
+    /// Whether a container chose its layout while being written on one line in alternate
+    /// display, so may be laid out differently when it isn't.
+    static FLATTENED: Cell<bool> = const { Cell::new(false) };
+}
+
+impl Layout {
+    /// The layout of the outermost container, which is assumed to start at column 0.
+    pub(crate) fn new() -> Self {
+        Layout {
+            width: None,
+            column: 0,
+            indent: 0,
+            depth: 0,
+            flat: false,
+        }
+    }
+
+    pub(crate) fn with_width(self, width: usize) -> Self {
+        Layout {
+            width: Some(width),
+            ..self
+        }
+    }
+
+    /// The layout of an item written with the given indent, either on its own line or
+    /// continuing the current line.
+    pub(crate) fn item(self, indent: &str, own_line: bool) -> Self {
+        let indent = self.indent + indent.chars().count();
+        Layout {
+            column: if own_line { indent } else { self.column },
+            indent,
+            depth: self.depth + 1,
+            ..self
+        }
+    }
+}
+
+/// The layout of the container being written, if it is laid out to a line width.
+pub(crate) fn current() -> Option<Layout> {
+    LAYOUT.get()
+}
+
+/// Set the layout until the returned guard is dropped.
+pub(crate) fn set(layout: Layout) -> LayoutGuard {
+    LayoutGuard(LAYOUT.replace(Some(layout)))
+}
+
+/// Write containers on one line, to check they fit, until the returned guard is dropped.
+pub(crate) fn set_flat() -> LayoutGuard {
+    let prev = LAYOUT.get();
+    LAYOUT.set(prev.map(|layout| Layout {
+        flat: true,
+        ..layout
+    }));
+    LayoutGuard(prev)
+}
+
+/// Record that a container chose its layout while being written on one line in alternate display.
+pub(crate) fn flattened() {
+    FLATTENED.set(true);
+}
+
+/// Call `write`, also returning whether any container it wrote called [`flattened`].
+pub(crate) fn track_flattened<R>(write: impl FnOnce() -> R) -> (R, bool) {
+    let prev = FLATTENED.replace(false);
+    let res = write();
+    let flattened = FLATTENED.get();
+    FLATTENED.set(prev || flattened);
+    (res, flattened)
+}
+
+/// Track the column after writing `s` outside of any item.
+///
+/// Output of items is tracked by a [`ColumnTracker`], but the outermost container writes its
+/// prefix directly.
+pub(crate) fn advance(s: &str) {
+    if let Some(mut layout) = LAYOUT.get()
+        && layout.depth == 0
+        && !layout.flat
+    {
+        layout.column = column_after(layout, s);
+        LAYOUT.set(Some(layout));
+    }
+}
+
+/// The column after writing `s` at the column of `layout`.
+fn column_after(layout: Layout, s: &str) -> usize {
+    match s.rfind('\n') {
+        Some(i) => layout.indent + s[i + 1..].chars().count(),
+        None => layout.column + s.chars().count(),
+    }
+}
+
+/// Save the layout, to restore it exactly when the returned guard is dropped.
+///
+/// Used when rendering output which is written later, so it doesn't move the column.
+pub(crate) fn save() -> SavedLayout {
+    SavedLayout(LAYOUT.get())
+}
+
+pub(crate) struct SavedLayout(Option<Layout>);
+
+impl Drop for SavedLayout {
+    fn drop(&mut self) {
+        LAYOUT.set(self.0);
+    }
+}
+
+/// Restores the previous layout when dropped, other than the column, which is where output
+/// continues from.
+pub(crate) struct LayoutGuard(Option<Layout>);
+
+impl Drop for LayoutGuard {
+    fn drop(&mut self) {
+        let column = LAYOUT.get().map(|layout| layout.column);
+        LAYOUT.set(self.0.map(|prev| Layout {
+            column: column.unwrap_or(prev.column),
+            ..prev
+        }));
+    }
+}
+
+/// Tracks the column of output written to an item.
+///
+/// Output of nested items passes through the trackers of all the enclosing items too,
+/// so only the tracker of the innermost item updates the column.
+pub(crate) struct ColumnTracker<W> {
+    inner: W,
+    depth: usize,
+}
+
+impl<W: Write> ColumnTracker<W> {
+    pub(crate) fn new(layout: Layout, inner: W) -> Self {
+        ColumnTracker {
+            inner,
+            depth: layout.depth,
+        }
+    }
+}
+
+impl<W: Write> Write for ColumnTracker<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if let Some(mut layout) = LAYOUT.get()
+            && layout.depth == self.depth
+        {
+            layout.column = column_after(layout, s);
+            LAYOUT.set(Some(layout));
+        }
+        self.inner.write_str(s)
+    }
+}
+
+/// Buffers output which fits on the rest of a line, failing once it doesn't.
+pub(crate) struct LineWriter {
+    line: String,
+    remaining: usize,
+    overflowed: bool,
+}
+
+impl LineWriter {
+    pub(crate) fn new(layout: Layout) -> Self {
+        LineWriter {
+            line: String::new(),
+            remaining: layout
+                .width
+                .map_or(usize::MAX, |width| width.saturating_sub(layout.column)),
+            overflowed: false,
+        }
+    }
+
+    /// Use up the space taken by `s`, which is written elsewhere.
+    pub(crate) fn skip(&mut self, s: &str) {
+        let len = s.chars().count();
+        if len > self.remaining || s.contains('\n') {
+            self.overflowed = true;
+        } else {
+            self.remaining -= len;
+        }
+    }
+
+    /// Whether writing failed because the output didn't fit.
+    pub(crate) fn overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        &self.line
+    }
+}
+
+impl Write for LineWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let len = s.chars().count();
+        if self.overflowed || len > self.remaining || s.contains('\n') {
+            self.overflowed = true;
+            return Err(fmt::Error);
+        }
+        self.remaining -= len;
+        self.line.push_str(s);
+        Ok(())
+    }
+}
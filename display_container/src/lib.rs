@@ -57,9 +57,25 @@
 //! ]
 //! ```
 //!
-//! This doesn't propagate the flags on the Formatter other than alternate.
-// TODO(cjhopman): Starlark values don't really do anything with the rest of the flags so
-// propagating them hasn't been necessary, but it would be easy enough to implement if we wanted to.
+//! The other flags on the Formatter (width, precision, fill, alignment and sign) are passed on to
+//! each item, so `format!("{:.2}", container)` displays each float item with two decimal places.
+//! To pad the container as a whole instead, wrap it with [`display_padded`].
+//!
+//! The layout for alternate display can be configured with a [`ContainerStyle`], including
+//! [`BreakMode::IfWiderThan`], which only breaks containers over multiple lines when they
+//! don't fit within a line width.
+//!
+//! To display huge or deeply nested values, such as in error messages, wrap them with
+//! [`display_with_limits`] to elide items past the given [`ContainerLimits`].
+//!
+//! Containers which produce their items through callbacks rather than iterators can be
+//! displayed with [`ContainerWriter`].
+
+mod flags;
+mod layout;
+mod limits;
+mod style;
+mod writer;
 
 use std::fmt;
 use std::fmt::Display;
@@ -67,17 +83,95 @@ use std::fmt::Write;
 
 use either::Either;
 
-const INDENT: &str = "  ";
+use crate::flags::Flags;
+use crate::layout::ColumnTracker;
+use crate::layout::Layout;
+use crate::layout::LayoutGuard;
+use crate::layout::LineWriter;
+pub use crate::limits::ContainerLimits;
+pub use crate::style::BreakMode;
+pub use crate::style::ContainerStyle;
+pub use crate::writer::ContainerWriter;
 
 /// Used to indent a displayed item for alternate display. This helps us pretty-print deep data structures.
-fn subwriter<T: Display>(indent: &'static str, f: &mut fmt::Formatter, v: T) -> fmt::Result {
+fn subwriter<T: Display>(
+    indent: &'static str,
+    own_line: bool,
+    f: &mut fmt::Formatter,
+    v: T,
+) -> fmt::Result {
     if f.alternate() {
-        write!(indenter::indented(f).with_str(indent), "{:#}", v)
+        let flags = Flags::of(f);
+        indented(indent, own_line, f, |w| flags.write(w, &v))
     } else {
         Display::fmt(&v, f)
     }
 }
 
+/// Write an item to `f` with each line indented.
+fn indented(
+    indent: &'static str,
+    own_line: bool,
+    f: &mut fmt::Formatter,
+    write: impl FnOnce(&mut dyn Write) -> fmt::Result,
+) -> fmt::Result {
+    let mut writer = indenter::indented(f).with_str(indent);
+    write_item(indent, own_line, &mut writer, write)
+}
+
+/// Write an item of a container, with the given indent applied to `out` already, tracking the
+/// column for nested containers laid out to a line width.
+fn write_item(
+    indent: &str,
+    own_line: bool,
+    out: &mut dyn Write,
+    write: impl FnOnce(&mut dyn Write) -> fmt::Result,
+) -> fmt::Result {
+    match layout::current() {
+        Some(layout) if !layout.flat => {
+            let layout = layout.item(indent, own_line);
+            let _guard = layout::set(layout);
+            write(&mut ColumnTracker::new(layout, out))
+        }
+        _ => write(out),
+    }
+}
+
+/// Whether containers may be displayed over multiple lines.
+fn alternate(f: &fmt::Formatter) -> bool {
+    if !f.alternate() {
+        return false;
+    }
+    // Containers being checked to fit on one line are written as in normal display.
+    if layout::current().is_some_and(|layout| layout.flat) {
+        layout::flattened();
+        return false;
+    }
+    true
+}
+
+/// Set the layout to display a container with, until the returned guard is dropped.
+///
+/// Returns the layout if the container is laid out to a line width. In alternate display the
+/// column is tracked even when it isn't, so nested containers know which column they start at.
+fn enter_layout(
+    f: &fmt::Formatter,
+    style: &ContainerStyle,
+) -> (Option<LayoutGuard>, Option<Layout>) {
+    if !alternate(f) {
+        return (None, None);
+    }
+    let current = layout::current();
+    match style.break_mode {
+        BreakMode::IfWiderThan(width) => {
+            let layout = current.unwrap_or_else(Layout::new).with_width(width);
+            (Some(layout::set(layout)), Some(layout))
+        }
+        _ if current.is_none() => (Some(layout::set(Layout::new())), None),
+        _ => (None, None),
+    }
+}
+
 /// Iterator length for display.
 enum Len {
     Zero,
@@ -85,6 +179,16 @@ enum Len {
     Many, // > 1
 }
 
+impl Len {
+    fn new(len: usize) -> Len {
+        match len {
+            0 => Len::Zero,
+            1 => Len::One,
+            _ => Len::Many,
+        }
+    }
+}
+
 /// Display a pair of elements with a separator in the middle.
 ///
 /// Equivalent to `write!(f, "{}{}{}", key, separator, value)`.
@@ -106,14 +210,84 @@ impl<K: Display, V: Display> Display for DisplayPair<'_, K, V> {
     }
 }
 
+/// Display a value padded as a whole to the width of the formatter.
+///
+/// Containers pass the width, fill and alignment on to each of their items. Wrapping a
+/// container with `display_padded` pads the whole container instead, while the other flags,
+/// such as precision, are still passed on to the items.
+///
+/// ```
+/// use display_container::*;
+///
+/// let items = display_container("[", "]", &[1.0, 2.5]);
+/// assert_eq!(format!("{:>5.1}", items), "[  1.0,   2.5]");
+/// assert_eq!(format!("{:>12.1}", display_padded(items)), "  [1.0, 2.5]");
+/// ```
+pub fn display_padded<T: Display>(v: T) -> impl Display {
+    struct Padded<T>(T);
+
+    impl<T: Display> Display for Padded<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let flags = Flags::of(f);
+            match flags.width {
+                None => Display::fmt(&self.0, f),
+                Some(width) => {
+                    let unpadded = flags.unpadded();
+                    let align = flags.align.unwrap_or(fmt::Alignment::Left);
+                    flags::pad(f, flags.fill, align, width, |w| unpadded.write(w, &self.0))
+                }
+            }
+        }
+    }
+
+    Padded(v)
+}
+
+/// Display a value, with the containers it displays limited by `limits`.
+///
+/// The limits apply to all containers displayed with this crate while displaying `v`,
+/// including through nested `Display` implementations.
+///
+/// ```
+/// use display_container::*;
+///
+/// let numbers: Vec<u32> = (0..100000).collect();
+/// let items = display_container("[", "]", &numbers);
+/// let limits = ContainerLimits::new().max_items(3);
+/// assert_eq!(
+///     display_with_limits(&items, limits).to_string(),
+///     "[0, 1, 2, ...(+99997 more)]"
+/// );
+/// let limits = ContainerLimits::new().max_len(12);
+/// assert_eq!(
+///     display_with_limits(&items, limits).to_string(),
+///     "[0, 1, 2, 3,..."
+/// );
+/// ```
+pub fn display_with_limits<T: Display>(v: T, limits: ContainerLimits) -> impl Display {
+    struct Limited<T>(T, ContainerLimits);
+
+    impl<T: Display> Display for Limited<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            limits::fmt_with_limits(f, self.1, &self.0)
+        }
+    }
+
+    Limited(v, limits)
+}
+
 /// The low-level helper for displaying containers. For simple containers, it may be more convenient to use `display_container` or `display_keyed_container`.
 struct ContainerDisplayHelper<'a, 'b> {
     f: &'a mut fmt::Formatter<'b>,
     /// The additional separator to be added after each item (except the last).
     separator: &'static str,
-    /// Extra output to be added after the prefix and before the suffix. Only non-empty for the single item case.
+    /// Whether each item is placed on its own line, in which case the separator is followed by a newline.
+    multiline: bool,
+    /// Whether the separator is also added after the last item.
+    trailing_separator: bool,
+    /// Extra output to be added after the prefix and before the suffix. Only non-empty for the single item and multiple lines cases.
     outer: &'static str,
-    /// The indent used for each item in the multiple items case (where each item is placed on its own line).
+    /// The indent used for each item in the multiple lines case (where each item is placed on its own line).
     indent: &'static str,
     /// A count of items, used for correctly adding the separator.
     seen_items: usize,
@@ -123,31 +297,50 @@ impl<'a, 'b> ContainerDisplayHelper<'a, 'b> {
     /// Begins displaying a container. The provided num_items will be used to select which formatting to use for alternate display.
     fn begin_inner(
         f: &'a mut fmt::Formatter<'b>,
+        style: &ContainerStyle,
         prefix: &str,
         num_items: Len,
     ) -> Result<Self, fmt::Error> {
-        let (separator, outer, indent) = match (f.alternate(), num_items) {
-            // We want to be formatted as `{prefix}item1, item2{suffix}`, like `[1, 2]` for lists.
-            (false, _) => (", ", "", ""),
-            // We want to be formatted as `{prefix}{suffix}`, like `[]` for lists.
-            (true, Len::Zero) => ("", "", ""),
-            // We want to be formatted as `{prefix} item {suffix}`, like `[ item ]` for lists
-            (true, Len::One) => ("", " ", ""),
-            // We want to be formatted as `{prefix}\n  item1,\n  item2\n{suffix}`, for lists like:
-            // ```
-            // [
-            //    item1,
-            //    item2
-            // ]
-            // ```
-            _ => (",\n", "\n", INDENT),
-        };
         f.write_str(prefix)?;
+        layout::advance(prefix);
+        Self::after_prefix(f, style, num_items)
+    }
+
+    /// Like `begin_inner`, when the prefix has already been written.
+    fn after_prefix(
+        f: &'a mut fmt::Formatter<'b>,
+        style: &ContainerStyle,
+        num_items: Len,
+    ) -> Result<Self, fmt::Error> {
+        // The separator is used if there are more items than `num_items`, which can happen
+        // with `ContainerWriter`.
+        let (separator, multiline, outer, indent) =
+            match (alternate(f), num_items, style.break_mode) {
+                // We want to be formatted as `{prefix}item1, item2{suffix}`, like `[1, 2]` for lists.
+                (false, _, _) => (style.separator, false, "", ""),
+                // We want to be formatted as `{prefix}{suffix}`, like `[]` for lists.
+                (true, Len::Zero, _) => (style.separator, false, "", ""),
+                // We want to be formatted as `{prefix} item {suffix}`, like `[ item ]` for lists
+                (true, Len::One, BreakMode::IfMany) => {
+                    (style.separator, false, style.single_item_spacing, "")
+                }
+                // We want to be formatted as `{prefix}\n  item1,\n  item2\n{suffix}`, for lists like:
+                // ```
+                // [
+                //    item1,
+                //    item2
+                // ]
+                // ```
+                _ => (style.multiline_separator, true, "\n", style.indent),
+            };
         f.write_str(outer)?;
+        layout::advance(outer);
 
         Ok(Self {
             f,
             separator,
+            multiline,
+            trailing_separator: multiline && style.trailing_separator,
             outer,
             indent,
             seen_items: 0,
@@ -157,14 +350,38 @@ impl<'a, 'b> ContainerDisplayHelper<'a, 'b> {
     /// Displays an item.
     pub fn item<T: Display>(&mut self, v: T) -> fmt::Result {
         if self.seen_items != 0 {
-            self.f.write_str(self.separator)?;
+            self.write_separator()?;
+        }
+        self.seen_items += 1;
+        subwriter(self.indent, self.multiline, self.f, &v)
+    }
+
+    /// Displays an item which was already written to a string with the flags of the formatter.
+    fn rendered_item(&mut self, v: &str) -> fmt::Result {
+        if self.seen_items != 0 {
+            self.write_separator()?;
         }
         self.seen_items += 1;
-        subwriter(self.indent, self.f, &v)
+        if self.f.alternate() {
+            indented(self.indent, self.multiline, self.f, |w| w.write_str(v))
+        } else {
+            self.f.write_str(v)
+        }
+    }
+
+    fn write_separator(&mut self) -> fmt::Result {
+        self.f.write_str(self.separator)?;
+        if self.multiline {
+            self.f.write_str("\n")?;
+        }
+        Ok(())
     }
 
     /// Ends displaying a container.
     pub fn end(self, suffix: &str) -> fmt::Result {
+        if self.trailing_separator && self.seen_items != 0 {
+            self.f.write_str(self.separator)?;
+        }
         self.f.write_str(self.outer)?;
         self.f.write_str(suffix)
     }
@@ -177,17 +394,42 @@ pub fn fmt_container<T: Display, Iter: IntoIterator<Item = T>>(
     suffix: &str,
     items: Iter,
 ) -> fmt::Result {
-    let mut items = items.into_iter();
+    fmt_container_with_style(f, &ContainerStyle::new(), prefix, suffix, items)
+}
+
+/// Like [`fmt_container`], but laid out with the given [`ContainerStyle`].
+pub fn fmt_container_with_style<T: Display, Iter: IntoIterator<Item = T>>(
+    f: &mut fmt::Formatter,
+    style: &ContainerStyle,
+    prefix: &str,
+    suffix: &str,
+    items: Iter,
+) -> fmt::Result {
+    let Some(_depth) = limits::enter() else {
+        f.write_str(prefix)?;
+        if items.into_iter().next().is_some() {
+            f.write_str("...")?;
+        }
+        return f.write_str(suffix);
+    };
+    let items = limits::limit_items(items.into_iter());
+
+    let (_layout, width_layout) = enter_layout(f, style);
+    if let Some(layout) = width_layout {
+        return fmt_container_to_width(f, style, layout, prefix, suffix, items);
+    }
+
+    let mut items = items;
     let helper = match items.next() {
-        None => ContainerDisplayHelper::begin_inner(f, prefix, Len::Zero)?,
+        None => ContainerDisplayHelper::begin_inner(f, style, prefix, Len::Zero)?,
         Some(first) => match items.next() {
             None => {
-                let mut helper = ContainerDisplayHelper::begin_inner(f, prefix, Len::One)?;
+                let mut helper = ContainerDisplayHelper::begin_inner(f, style, prefix, Len::One)?;
                 helper.item(first)?;
                 helper
             }
             Some(second) => {
-                let mut helper = ContainerDisplayHelper::begin_inner(f, prefix, Len::Many)?;
+                let mut helper = ContainerDisplayHelper::begin_inner(f, style, prefix, Len::Many)?;
                 helper.item(first)?;
                 helper.item(second)?;
                 for v in items {
@@ -200,6 +442,57 @@ pub fn fmt_container<T: Display, Iter: IntoIterator<Item = T>>(
     helper.end(suffix)
 }
 
+/// Display a container on one line if it fits within the line width, and otherwise with each item
+/// on its own line.
+///
+/// Only as many items as fit on a line are written before finding the container doesn't fit, so
+/// those items are kept to be written again, rather than needing the items to be iterated twice.
+fn fmt_container_to_width<T: Display>(
+    f: &mut fmt::Formatter,
+    style: &ContainerStyle,
+    layout: Layout,
+    prefix: &str,
+    suffix: &str,
+    mut items: impl Iterator<Item = T>,
+) -> fmt::Result {
+    let mut seen = Vec::new();
+    let mut line = LineWriter::new(layout);
+    let fits = {
+        let flags = Flags::of(f);
+        let _guard = layout::set_flat();
+        let mut write_line = || {
+            line.write_str(prefix)?;
+            for v in items.by_ref() {
+                seen.push(v);
+                if seen.len() != 1 {
+                    line.write_str(style.separator)?;
+                }
+                flags.write(&mut line, seen.last().unwrap())?;
+            }
+            line.write_str(suffix)
+        };
+        match write_line() {
+            Ok(()) => true,
+            Err(_) if line.overflowed() => false,
+            Err(e) => return Err(e),
+        }
+    };
+    if fits {
+        return f.write_str(line.as_str());
+    }
+
+    let mut items = seen.into_iter().chain(items).peekable();
+    let len = match items.peek() {
+        None => Len::Zero,
+        Some(_) => Len::Many,
+    };
+    let mut helper = ContainerDisplayHelper::begin_inner(f, style, prefix, len)?;
+    for v in items {
+        helper.item(v)?;
+    }
+    helper.end(suffix)
+}
+
 /// Helper for display implementation of container-y types (like list, tuple).
 pub fn display_container<'a, C>(prefix: &'a str, suffix: &'a str, items: C) -> impl Display + 'a
 where
@@ -237,8 +530,21 @@ pub fn fmt_keyed_container<K: Display, V: Display, Iter: IntoIterator<Item = (K,
     separator: &str,
     items: Iter,
 ) -> fmt::Result {
-    fmt_container(
+    fmt_keyed_container_with_style(f, &ContainerStyle::new(), prefix, suffix, separator, items)
+}
+
+/// Like [`fmt_keyed_container`], but laid out with the given [`ContainerStyle`].
+pub fn fmt_keyed_container_with_style<K: Display, V: Display, Iter: IntoIterator<Item = (K, V)>>(
+    f: &mut fmt::Formatter,
+    style: &ContainerStyle,
+    prefix: &str,
+    suffix: &str,
+    separator: &str,
+    items: Iter,
+) -> fmt::Result {
+    fmt_container_with_style(
         f,
+        style,
         prefix,
         suffix,
         items
@@ -333,6 +639,271 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_container_style() {
+        struct Styled(ContainerStyle, Vec<(u32, u32)>);
+        impl fmt::Display for Styled {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt_keyed_container_with_style(f, &self.0, "{", "}", ": ", self.1.iter().copied())
+            }
+        }
+
+        let style = ContainerStyle::new()
+            .indent("\t")
+            .separator(" ; ")
+            .multiline_separator(";")
+            .trailing_separator(true)
+            .single_item_spacing("");
+        assert_eq!(
+            "{1: 2 ; 3: 4}",
+            format!("{}", Styled(style, vec![(1, 2), (3, 4)]))
+        );
+        assert_eq!("{}", format!("{:#}", Styled(style, vec![])));
+        assert_eq!("{1: 2}", format!("{:#}", Styled(style, vec![(1, 2)])));
+        assert_eq!(
+            "{\n\t1: 2;\n\t3: 4;\n}",
+            format!("{:#}", Styled(style, vec![(1, 2), (3, 4)]))
+        );
+
+        let style = ContainerStyle::default().break_mode(BreakMode::Always);
+        assert_eq!("{}", format!("{:#}", Styled(style, vec![])));
+        assert_eq!("{\n  1: 2\n}", format!("{:#}", Styled(style, vec![(1, 2)])));
+        assert_eq!("{1: 2}", format!("{}", Styled(style, vec![(1, 2)])));
+    }
+
+    #[test]
+    fn test_line_width() {
+        const STYLE: ContainerStyle = ContainerStyle::new().break_mode(BreakMode::IfWiderThan(17));
+        struct List(Vec<List>, u32);
+        impl fmt::Display for List {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                if self.0.is_empty() {
+                    write!(f, "{}", self.1)
+                } else {
+                    fmt_container_with_style(f, &STYLE, "[", "]", &self.0)
+                }
+            }
+        }
+        struct Dict(Vec<(&'static str, List)>);
+        impl fmt::Display for Dict {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt_keyed_container_with_style(
+                    f,
+                    &STYLE,
+                    "{",
+                    "}",
+                    ": ",
+                    self.0.iter().map(|(k, v)| (k, v)),
+                )
+            }
+        }
+        fn list(xs: &[u32]) -> List {
+            List(xs.iter().map(|x| List(Vec::new(), *x)).collect(), 0)
+        }
+
+        let fits = Dict(vec![("a", list(&[1, 2])), ("b", list(&[]))]);
+        // Exactly 17 characters.
+        assert_eq!("{a: [1, 2], b: 0}", format!("{:#}", fits));
+        let too_wide = Dict(vec![("a", list(&[1, 2])), ("b", list(&[3]))]);
+        assert_eq!("{a: [1, 2], b: [3]}", format!("{}", too_wide));
+        assert_eq!("{\n  a: [1, 2],\n  b: [3]\n}", format!("{:#}", too_wide));
+
+        let nested = Dict(vec![
+            ("short", list(&[1, 2])),
+            ("long", list(&[100, 200, 300, 400])),
+            (
+                "deep",
+                List(vec![list(&[1, 2]), list(&[30000, 40000, 50000])], 0),
+            ),
+        ]);
+        assert_eq!(
+            "{\n  short: [1, 2],\n  long: [\n    100,\n    200,\n    300,\n    400\n  ],\n  deep: [\n    [1, 2],\n    [\n      30000,\n      40000,\n      50000\n    ]\n  ]\n}",
+            format!("{:#}", nested)
+        );
+        assert_eq!("{}", format!("{:#}", Dict(vec![])));
+        // The list would fit on its own, but not after the key.
+        assert_eq!(
+            "{\n  a_long_key: [\n    1,\n    2\n  ],\n  b: 0\n}",
+            format!(
+                "{:#}",
+                Dict(vec![("a_long_key", list(&[1, 2])), ("b", list(&[]))])
+            )
+        );
+    }
+
+    #[test]
+    fn test_line_width_nested_style() {
+        struct Dict<T>(ContainerStyle, Vec<(&'static str, T)>);
+        impl<T: Display> fmt::Display for Dict<T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt_keyed_container_with_style(
+                    f,
+                    &self.0,
+                    "{",
+                    "}",
+                    ": ",
+                    self.1.iter().map(|(k, v)| (k, v)),
+                )
+            }
+        }
+        struct List(ContainerStyle, Vec<u32>);
+        impl fmt::Display for List {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt_container_with_style(f, &self.0, "[", "]", &self.1)
+            }
+        }
+        let wide = ContainerStyle::new().break_mode(BreakMode::IfWiderThan(12));
+        let many = ContainerStyle::new();
+
+        // Nested containers are laid out with their own break mode.
+        let dict = Dict(
+            wide,
+            vec![("a", List(many, vec![1, 2])), ("b", List(many, vec![3]))],
+        );
+        assert_eq!(
+            "{\n  a: [\n    1,\n    2\n  ],\n  b: [ 3 ]\n}",
+            format!("{:#}", dict)
+        );
+        let dict = Dict(wide, vec![("a", List(many, vec![1, 2]))]);
+        assert_eq!("{a: [1, 2]}", format!("{:#}", dict));
+
+        // Containers laid out to a line width start from the column they are written at.
+        let list = || List(wide, vec![1, 2]);
+        assert_eq!("[1, 2]", format!("{:#}", list()));
+        assert_eq!(
+            "{ k: [1, 2] }",
+            format!("{:#}", Dict(many, vec![("k", list())]))
+        );
+        assert_eq!(
+            "{ long_key: [\n  1,\n  2\n] }",
+            format!("{:#}", Dict(many, vec![("long_key", list())]))
+        );
+        assert_eq!(
+            "{\n  a: [1, 2],\n  long_key: [\n    1,\n    2\n  ]\n}",
+            format!(
+                "{:#}",
+                Dict(many, vec![("a", list()), ("long_key", list())])
+            )
+        );
+    }
+
+    #[test]
+    fn test_limits() {
+        struct Tree(Vec<Tree>);
+        impl fmt::Display for Tree {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt_container(f, "[", "]", &self.0)
+            }
+        }
+        fn tree(depth: usize, width: usize) -> Tree {
+            Tree(if depth == 0 {
+                Vec::new()
+            } else {
+                (0..width).map(|_| tree(depth - 1, width)).collect()
+            })
+        }
+
+        let limits = ContainerLimits::new().max_items(2);
+        assert_eq!("[]", display_with_limits(tree(1, 0), limits).to_string());
+        assert_eq!(
+            "[[[], []], [[], []]]",
+            display_with_limits(tree(2, 2), limits).to_string()
+        );
+        assert_eq!(
+            "[[[], [], ...(+1 more)], [[], [], ...(+1 more)], ...(+1 more)]",
+            display_with_limits(tree(2, 3), limits).to_string()
+        );
+        assert_eq!(
+            "[\n  [],\n  [],\n  ...(+1 more)\n]",
+            format!("{:#}", display_with_limits(tree(1, 3), limits))
+        );
+        // The rest are not counted if the iterator doesn't know how many there are.
+        struct Odd(usize);
+        impl Display for Odd {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt_container(f, "[", "]", (0..self.0).filter(|i| i % 2 == 1))
+            }
+        }
+        assert_eq!(
+            "[1, 3, ...]",
+            display_with_limits(Odd(7), limits).to_string()
+        );
+        assert_eq!("[1, 3]", display_with_limits(Odd(4), limits).to_string());
+
+        let limits = ContainerLimits::new().max_depth(2);
+        assert_eq!(
+            "[[[...], [...]], [[...], [...]]]",
+            display_with_limits(tree(3, 2), limits).to_string()
+        );
+        assert_eq!(
+            "[[[], []], [[], []]]",
+            display_with_limits(tree(2, 2), limits).to_string()
+        );
+
+        let limits = ContainerLimits::new().max_len(10);
+        assert_eq!(
+            "[[], []]",
+            display_with_limits(tree(1, 2), limits).to_string()
+        );
+        assert_eq!(
+            "[[[], []],...",
+            display_with_limits(tree(2, 2), limits).to_string()
+        );
+        assert_eq!(
+            "[\n  [],\n  ...",
+            format!("{:#}", display_with_limits(tree(1, 3), limits))
+        );
+    }
+
+    #[test]
+    fn test_limits_padded() {
+        use std::cell::Cell;
+
+        /// Counts how many times it is displayed.
+        struct Counted<'a>(&'a Cell<usize>);
+        impl fmt::Display for Counted<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.set(self.0.get() + 1);
+                f.write_str("x")
+            }
+        }
+
+        let count = Cell::new(0);
+        let items: Vec<_> = (0..1000).map(|_| Counted(&count)).collect();
+        let items = display_container("[", "]", &items);
+        let limits = ContainerLimits::new().max_len(10);
+        // Items stop being displayed once the output is truncated, including when padded.
+        let check = |padded: String| {
+            assert_eq!("[x, x, x, ...", padded);
+            assert!(count.get() < 10, "displayed {} items", count.get());
+            count.set(0);
+        };
+        check(format!("{:*>8}", display_with_limits(&items, limits)));
+        check(format!("{:*^8}", display_with_limits(&items, limits)));
+        check(format!("{:*<8}", display_with_limits(&items, limits)));
+        check(format!(
+            "{:>8}",
+            display_with_limits(display_padded(&items), limits)
+        ));
+        check(format!(
+            "{:*>8}",
+            display_with_limits(display_padded(&items), limits)
+        ));
+        let short = display_container("[", "]", &[1, 2]);
+        assert_eq!(
+            "**[1, 2]",
+            format!("{:*>8}", display_with_limits(&short, limits))
+        );
+        assert_eq!(
+            "*[1, 2]*",
+            format!("{:*^8}", display_with_limits(&short, limits))
+        );
+        assert_eq!(
+            "[1, 2]**",
+            format!("{:*<8}", display_with_limits(&short, limits))
+        );
+    }
+
     #[test]
     fn test_combinators() {
         struct MyItems(Vec<(String, i32)>);
@@ -359,4 +930,40 @@ mod tests {
     fn test_display_container() {
         assert_eq!("[1]", display_container("[", "]", &vec![1]).to_string());
     }
+
+    #[test]
+    fn test_flags() {
+        let floats = display_container("[", "]", &[1.0, -2.25]);
+        assert_eq!("[1.00, -2.25]", format!("{:.2}", floats));
+        assert_eq!("[\n  1.00,\n  -2.25\n]", format!("{:#.2}", floats));
+        assert_eq!("[+1.0, -2.2]", format!("{:+.1}", floats));
+        assert_eq!(
+            "[\n   1,\n  22\n]",
+            format!("{:>#2}", display_container("[", "]", &[1, 22]))
+        );
+        assert_eq!(
+            "[\n  *1*,\n  22*\n]",
+            format!("{:*^#3}", display_container("[", "]", &[1, 22]))
+        );
+        assert_eq!(
+            "[\n  a  = 01,\n  b  = 02\n]",
+            format!(
+                "{:#02}",
+                display_container(
+                    "[",
+                    "]",
+                    &[display_pair("a", " = ", 1), display_pair("b", " = ", 2)]
+                )
+            )
+        );
+    }
+
+    #[test]
+    fn test_display_padded() {
+        let ints = display_container("[", "]", &[1, 2]);
+        assert_eq!("[1, 2]  ", format!("{:8}", display_padded(&ints)));
+        assert_eq!("**[1, 2]", format!("{:*>8}", display_padded(&ints)));
+        assert_eq!("[\n  1,\n  2\n]", format!("{:#8}", display_padded(&ints)));
+        assert_eq!("[1, 2]", format!("{:3}", display_padded(&ints)));
+    }
 }
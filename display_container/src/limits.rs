@@ -0,0 +1,263 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+//! Limits on how much of nested containers is displayed.
+//!
+//! The limits are set by [`display_with_limits`](crate::display_with_limits), and kept in a
+//! thread-local so they apply to containers displayed by nested `Display` implementations.
+
+use std::cell::Cell;
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Write;
+
+use either::Either;
+
+/// Limits on how much of a value is displayed by
+/// [`display_with_limits`](crate::display_with_limits).
+///
+/// By default there are no limits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ContainerLimits {
+    max_items: Option<usize>,
+    max_depth: Option<usize>,
+    max_len: Option<usize>,
+}
+
+impl ContainerLimits {
+    /// No limits.
+    pub const fn new() -> Self {
+        ContainerLimits {
+            max_items: None,
+            max_depth: None,
+            max_len: None,
+        }
+    }
+
+    /// Display at most this many items of each container, followed by a `...(+N more)` item,
+    /// or just `...` if the container's iterator doesn't know its exact length.
+    pub const fn max_items(mut self, max_items: usize) -> Self {
+        self.max_items = Some(max_items);
+        self
+    }
+
+    /// Display at most this many levels of nested containers. Deeper containers are displayed
+    /// as their prefix and suffix around `...`, like `[...]`.
+    pub const fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Display at most this many characters, followed by `...` if the output was truncated.
+    /// Containers stop displaying items once the output is truncated.
+    pub const fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = Some(max_len);
+        self
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Context {
+    limits: ContainerLimits,
+    /// The number of containers being displayed.
+    depth: usize,
+    /// Whether the output has been truncated to `max_len`.
+    truncated: bool,
+}
+
+thread_local! {
+    static LIMITS: Cell<Option<Context>> = const { Cell::new(None) };
+}
+
+/// Display `v` with the limits applied, through the given formatter.
+pub(crate) fn fmt_with_limits(
+    f: &mut fmt::Formatter,
+    limits: ContainerLimits,
+    v: &dyn Display,
+) -> fmt::Result {
+    let prev = LIMITS.replace(Some(Context {
+        limits,
+        depth: 0,
+        truncated: false,
+    }));
+    // Restore the previous limits, even if displaying panics.
+    struct Restore(Option<Context>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            LIMITS.set(self.0);
+        }
+    }
+    let _restore = Restore(prev);
+
+    match limits.max_len {
+        None => Display::fmt(v, f),
+        Some(max_len) => {
+            let flags = crate::flags::Flags::of(f);
+            flags.write(
+                &mut Truncate {
+                    inner: &mut *f,
+                    remaining: max_len,
+                },
+                v,
+            )?;
+            if truncated() {
+                f.write_str("...")?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Whether the output has been truncated to `max_len`, so there is no need to display more.
+pub(crate) fn truncated() -> bool {
+    LIMITS.get().is_some_and(|ctx| ctx.truncated)
+}
+
+/// Render output which is written later, with at most `max_len` characters.
+///
+/// Containers stop displaying items once the buffer is full, but the output is only marked as
+/// truncated once the buffer is written to it.
+pub(crate) fn buffered(
+    write: impl FnOnce(&mut dyn Write) -> fmt::Result,
+) -> Result<String, fmt::Error> {
+    let Some(prev) = LIMITS.get() else {
+        let mut buffer = String::new();
+        write(&mut buffer)?;
+        return Ok(buffer);
+    };
+    let mut buffer = Truncate {
+        inner: String::new(),
+        remaining: prev.limits.max_len.unwrap_or(usize::MAX),
+    };
+    let res = write(&mut buffer);
+    if let Some(mut ctx) = LIMITS.get() {
+        ctx.truncated = prev.truncated;
+        LIMITS.set(Some(ctx));
+    }
+    res.map(|()| buffer.inner)
+}
+
+/// Writes at most `remaining` characters, and drops the rest.
+struct Truncate<W> {
+    inner: W,
+    remaining: usize,
+}
+
+impl<W: Write> Write for Truncate<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        match s.char_indices().nth(self.remaining) {
+            None => {
+                self.remaining -= s.chars().count();
+                self.inner.write_str(s)
+            }
+            Some((end, _)) => {
+                self.remaining = 0;
+                if let Some(mut ctx) = LIMITS.get() {
+                    ctx.truncated = true;
+                    LIMITS.set(Some(ctx));
+                }
+                self.inner.write_str(&s[..end])
+            }
+        }
+    }
+}
+
+/// Enter a container, returning `None` if it is nested too deeply to display its items.
+pub(crate) fn enter() -> Option<DepthGuard> {
+    match LIMITS.get() {
+        None => Some(DepthGuard(false)),
+        Some(ctx) if ctx.limits.max_depth.is_some_and(|max| ctx.depth >= max) => None,
+        Some(mut ctx) => {
+            ctx.depth += 1;
+            LIMITS.set(Some(ctx));
+            Some(DepthGuard(true))
+        }
+    }
+}
+
+/// Leaves the container when dropped.
+pub(crate) struct DepthGuard(bool);
+
+impl Drop for DepthGuard {
+    fn drop(&mut self) {
+        if self.0
+            && let Some(mut ctx) = LIMITS.get()
+        {
+            ctx.depth -= 1;
+            LIMITS.set(Some(ctx));
+        }
+    }
+}
+
+/// The items of a container to display: at most `max_items` items followed by a marker for the
+/// rest, stopping once the output is truncated.
+pub(crate) fn limit_items<T: Display>(
+    items: impl Iterator<Item = T>,
+) -> impl Iterator<Item = Either<T, Elided>> {
+    LimitItems {
+        items,
+        max_items: max_items(),
+        done: false,
+    }
+}
+
+/// The maximum number of items to display of each container.
+pub(crate) fn max_items() -> Option<usize> {
+    LIMITS.get().and_then(|ctx| ctx.limits.max_items)
+}
+
+struct LimitItems<I> {
+    items: I,
+    /// The number of items still to display, if limited.
+    max_items: Option<usize>,
+    done: bool,
+}
+
+impl<I: Iterator> Iterator for LimitItems<I> {
+    type Item = Either<I::Item, Elided>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || truncated() {
+            self.done = true;
+            return None;
+        }
+        match &mut self.max_items {
+            Some(0) => {
+                self.done = true;
+                // Counting the rest would iterate over them, so only report the count when
+                // it is known.
+                match self.items.size_hint() {
+                    (0, Some(0)) => None,
+                    (lower, Some(upper)) if lower == upper => {
+                        Some(Either::Right(Elided(Some(lower))))
+                    }
+                    _ => self.items.next().map(|_| Either::Right(Elided(None))),
+                }
+            }
+            Some(n) => {
+                *n -= 1;
+                self.items.next().map(Either::Left)
+            }
+            None => self.items.next().map(Either::Left),
+        }
+    }
+}
+
+/// Displayed in place of the items of a container over `max_items`, with their number if known.
+pub(crate) struct Elided(pub(crate) Option<usize>);
+
+impl Display for Elided {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(more) => write!(f, "...(+{more} more)"),
+            None => f.write_str("..."),
+        }
+    }
+}
@@ -0,0 +1,122 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+/// When a container is broken over multiple lines in alternate display.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BreakMode {
+    /// Containers with more than one item are broken, so each item is on its own line.
+    IfMany,
+    /// Containers with any items are broken, even if there is only one item.
+    Always,
+    /// Containers are written on one line if they fit within the given line width, including
+    /// the indent and anything before them on the line, and broken otherwise. Nested containers
+    /// are laid out with their own style.
+    ///
+    /// The column is tracked from the start of the outermost container displayed in alternate
+    /// mode, which is assumed to start at column 0.
+    IfWiderThan(usize),
+}
+
+/// How containers are laid out by [`fmt_container_with_style`](crate::fmt_container_with_style)
+/// and [`fmt_keyed_container_with_style`](crate::fmt_keyed_container_with_style).
+///
+/// The default style produces the same output as [`fmt_container`](crate::fmt_container).
+/// Everything other than the inline separator only applies to alternate display.
+///
+/// ```
+/// use std::fmt;
+///
+/// use display_container::*;
+///
+/// const BLACK: ContainerStyle = ContainerStyle::new()
+///     .indent("    ")
+///     .trailing_separator(true)
+///     .single_item_spacing("");
+///
+/// struct List(Vec<u32>);
+///
+/// impl fmt::Display for List {
+///     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+///         fmt_container_with_style(f, &BLACK, "[", "]", &self.0)
+///     }
+/// }
+///
+/// assert_eq!(format!("{:#}", List(vec![1])), "[1]");
+/// assert_eq!(format!("{:#}", List(vec![1, 2])), "[\n    1,\n    2,\n]");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ContainerStyle {
+    pub(crate) indent: &'static str,
+    pub(crate) separator: &'static str,
+    pub(crate) multiline_separator: &'static str,
+    pub(crate) trailing_separator: bool,
+    pub(crate) single_item_spacing: &'static str,
+    pub(crate) break_mode: BreakMode,
+}
+
+impl ContainerStyle {
+    /// The default style.
+    pub const fn new() -> Self {
+        ContainerStyle {
+            indent: "  ",
+            separator: ", ",
+            multiline_separator: ",",
+            trailing_separator: false,
+            single_item_spacing: " ",
+            break_mode: BreakMode::IfMany,
+        }
+    }
+
+    /// The indent of each item when the container is broken over multiple lines.
+    /// Defaults to two spaces.
+    pub const fn indent(mut self, indent: &'static str) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    /// The separator between items on a single line. Defaults to `", "`.
+    pub const fn separator(mut self, separator: &'static str) -> Self {
+        self.separator = separator;
+        self
+    }
+
+    /// The separator at the end of each line, before the newline, when the container is
+    /// broken over multiple lines. Defaults to `","`.
+    pub const fn multiline_separator(mut self, separator: &'static str) -> Self {
+        self.multiline_separator = separator;
+        self
+    }
+
+    /// Whether the multiline separator is also written after the last item. Defaults to `false`.
+    pub const fn trailing_separator(mut self, trailing: bool) -> Self {
+        self.trailing_separator = trailing;
+        self
+    }
+
+    /// The spacing after the prefix and before the suffix of a container with a single item
+    /// on one line, as in `[ item ]`. Defaults to a single space.
+    pub const fn single_item_spacing(mut self, spacing: &'static str) -> Self {
+        self.single_item_spacing = spacing;
+        self
+    }
+
+    /// When the container is broken over multiple lines. Defaults to [`BreakMode::IfMany`].
+    pub const fn break_mode(mut self, break_mode: BreakMode) -> Self {
+        self.break_mode = break_mode;
+        self
+    }
+}
+
+impl Default for ContainerStyle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
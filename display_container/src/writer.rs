@@ -0,0 +1,568 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Write;
+use std::mem;
+use std::ops::Range;
+
+use crate::ContainerDisplayHelper;
+use crate::ContainerStyle;
+use crate::Len;
+use crate::alternate;
+use crate::display_pair;
+use crate::enter_layout;
+use crate::flags::Flags;
+use crate::layout;
+use crate::layout::LayoutGuard;
+use crate::layout::LineWriter;
+use crate::limits;
+use crate::limits::DepthGuard;
+use crate::limits::Elided;
+use crate::write_item;
+
+/// Displays a container one item at a time, for containers which can't provide an iterator
+/// for [`fmt_container`](crate::fmt_container), such as those visiting their items with a
+/// callback.
+///
+/// The output is the same as [`fmt_container_with_style`](crate::fmt_container_with_style).
+/// Choosing the layout for alternate display needs the number of items, so when the length
+/// isn't given, the first item is written to a buffer until the second item or the end.
+/// Containers laid out to a line width within that item are laid out as if it were on its own
+/// line, even if it turns out to be the only item.
+///
+/// ```
+/// use std::fmt;
+///
+/// use display_container::*;
+///
+/// struct Visited(Vec<(&'static str, u32)>);
+///
+/// impl Visited {
+///     fn visit(&self, mut visitor: impl FnMut(&str, u32) -> fmt::Result) -> fmt::Result {
+///         self.0.iter().try_for_each(|(k, v)| visitor(k, *v))
+///     }
+/// }
+///
+/// impl fmt::Display for Visited {
+///     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+///         let mut writer = ContainerWriter::begin(f, "{", None)?;
+///         self.visit(|k, v| writer.keyed_item(k, ": ", v))?;
+///         writer.end("}")
+///     }
+/// }
+///
+/// assert_eq!(format!("{:#}", Visited(vec![("a", 1)])), "{ a: 1 }");
+/// assert_eq!(
+///     format!("{:#}", Visited(vec![("a", 1), ("b", 2)])),
+///     "{\n  a: 1,\n  b: 2\n}"
+/// );
+/// ```
+pub struct ContainerWriter<'a, 'b> {
+    state: State<'a, 'b>,
+    style: ContainerStyle,
+    /// The number of items still to display, if limited by `display_with_limits`.
+    max_items: Option<usize>,
+    /// The number of items not displayed because of `max_items`.
+    elided: usize,
+    _layout: Option<LayoutGuard>,
+    _depth: Option<DepthGuard>,
+}
+
+enum State<'a, 'b> {
+    /// The container is nested too deeply to display its items.
+    Elided {
+        f: &'a mut fmt::Formatter<'b>,
+        any_items: bool,
+    },
+    /// The layout is known, so items are written directly.
+    Direct(ContainerDisplayHelper<'a, 'b>),
+    /// The layout depends on the number of items, so the first item is buffered.
+    Buffered {
+        f: &'a mut fmt::Formatter<'b>,
+        first: Option<String>,
+    },
+    /// The container is laid out to a line width, and the items written so far fit on the line.
+    Fitting {
+        f: &'a mut fmt::Formatter<'b>,
+        line: LineWriter,
+        /// The items written so far, to write again if the line is broken.
+        items: Vec<LineItem>,
+    },
+    /// Writing failed.
+    Failed,
+}
+
+impl<'a, 'b> ContainerWriter<'a, 'b> {
+    /// Begin displaying a container, with the number of items if it is known.
+    ///
+    /// The number of items is only used to choose the layout. If a different number of items
+    /// is given, they are all still displayed, but laid out as if there were `len` of them.
+    pub fn begin(
+        f: &'a mut fmt::Formatter<'b>,
+        prefix: &str,
+        len: Option<usize>,
+    ) -> Result<Self, fmt::Error> {
+        Self::begin_with_style(f, &ContainerStyle::new(), prefix, len)
+    }
+
+    /// Like [`begin`](ContainerWriter::begin), but laid out with the given [`ContainerStyle`].
+    pub fn begin_with_style(
+        f: &'a mut fmt::Formatter<'b>,
+        style: &ContainerStyle,
+        prefix: &str,
+        len: Option<usize>,
+    ) -> Result<Self, fmt::Error> {
+        let depth = limits::enter();
+        let (layout_guard, layout) = match depth {
+            Some(_) => enter_layout(f, style),
+            None => (None, None),
+        };
+        let state = if depth.is_none() {
+            f.write_str(prefix)?;
+            State::Elided {
+                f,
+                any_items: false,
+            }
+        } else if let Some(layout) = layout {
+            let mut line = LineWriter::new(layout);
+            line.skip(prefix);
+            f.write_str(prefix)?;
+            State::Fitting {
+                f,
+                line,
+                items: Vec::new(),
+            }
+        } else if let Some(len) = len {
+            State::Direct(ContainerDisplayHelper::begin_inner(
+                f,
+                style,
+                prefix,
+                Len::new(len),
+            )?)
+        } else if !alternate(f) {
+            // All items are on one line, whatever the number of items.
+            State::Direct(ContainerDisplayHelper::begin_inner(
+                f,
+                style,
+                prefix,
+                Len::Many,
+            )?)
+        } else {
+            f.write_str(prefix)?;
+            layout::advance(prefix);
+            State::Buffered { f, first: None }
+        };
+        Ok(ContainerWriter {
+            state,
+            style: *style,
+            max_items: limits::max_items(),
+            elided: 0,
+            _layout: layout_guard,
+            _depth: depth,
+        })
+    }
+
+    /// Displays an item.
+    pub fn item<T: Display>(&mut self, v: T) -> fmt::Result {
+        if limits::truncated() {
+            return Ok(());
+        }
+        match &mut self.max_items {
+            Some(0) => {
+                self.elided += 1;
+                return Ok(());
+            }
+            Some(n) => *n -= 1,
+            None => {}
+        }
+        self.write_item(&v)
+    }
+
+    /// Displays a pair of a key and value with a separator in the middle, like
+    /// [`fmt_keyed_container`](crate::fmt_keyed_container).
+    pub fn keyed_item<K: Display, V: Display>(
+        &mut self,
+        key: K,
+        separator: &str,
+        value: V,
+    ) -> fmt::Result {
+        self.item(display_pair(key, separator, value))
+    }
+
+    fn write_item(&mut self, v: &dyn Display) -> fmt::Result {
+        self.state = match mem::replace(&mut self.state, State::Failed) {
+            State::Elided { f, .. } => State::Elided { f, any_items: true },
+            State::Direct(mut helper) => {
+                helper.item(v)?;
+                State::Direct(helper)
+            }
+            State::Buffered { f, first: None } => {
+                let first = render_item(&self.style, Flags::of(f), v)?;
+                State::Buffered {
+                    f,
+                    first: Some(first),
+                }
+            }
+            State::Buffered {
+                f,
+                first: Some(first),
+            } => {
+                let mut helper = ContainerDisplayHelper::after_prefix(f, &self.style, Len::Many)?;
+                helper.rendered_item(&first)?;
+                helper.item(v)?;
+                State::Direct(helper)
+            }
+            State::Fitting {
+                f,
+                mut line,
+                mut items,
+            } => match self.write_flat(f, &mut line, items.is_empty(), v) {
+                Ok((range, false)) => {
+                    items.push(LineItem::Flat(range));
+                    State::Fitting { f, line, items }
+                }
+                Ok((_, true)) => {
+                    // The values are gone if the line is broken later, so items which may be
+                    // laid out differently on their own line are rendered that way now.
+                    items.push(LineItem::Rendered(render_item(
+                        &self.style,
+                        Flags::of(f),
+                        v,
+                    )?));
+                    State::Fitting { f, line, items }
+                }
+                Err(_) if line.overflowed() => {
+                    let mut helper = break_line(f, &self.style, &line, &items, Len::Many)?;
+                    helper.item(v)?;
+                    State::Direct(helper)
+                }
+                Err(e) => return Err(e),
+            },
+            State::Failed => return Err(fmt::Error),
+        };
+        Ok(())
+    }
+
+    /// Write an item to the line, to check it fits. Returns where the item is on the line, and
+    /// whether it contains containers which may be laid out differently on their own line.
+    fn write_flat(
+        &self,
+        f: &fmt::Formatter,
+        line: &mut LineWriter,
+        first: bool,
+        v: &dyn Display,
+    ) -> Result<(Range<usize>, bool), fmt::Error> {
+        let _guard = layout::set_flat();
+        if !first {
+            line.write_str(self.style.separator)?;
+        }
+        let start = line.as_str().len();
+        let (res, flattened) = layout::track_flattened(|| Flags::of(f).write(line, v));
+        res?;
+        Ok((start..line.as_str().len(), flattened))
+    }
+
+    /// Ends displaying a container.
+    pub fn end(mut self, suffix: &str) -> fmt::Result {
+        if self.elided != 0 {
+            self.write_item(&Elided(Some(self.elided)))?;
+        }
+        match self.state {
+            State::Elided { f, any_items } => {
+                if any_items {
+                    f.write_str("...")?;
+                }
+                f.write_str(suffix)
+            }
+            State::Direct(helper) => helper.end(suffix),
+            State::Buffered { f, first } => {
+                let len = match first {
+                    None => Len::Zero,
+                    Some(_) => Len::One,
+                };
+                let mut helper = ContainerDisplayHelper::after_prefix(f, &self.style, len)?;
+                if let Some(first) = first {
+                    helper.rendered_item(&first)?;
+                }
+                helper.end(suffix)
+            }
+            State::Fitting { f, mut line, items } => {
+                if line.write_str(suffix).is_ok() {
+                    return f.write_str(line.as_str());
+                }
+                let len = match items.len() {
+                    0 => Len::Zero,
+                    _ => Len::Many,
+                };
+                break_line(f, &self.style, &line, &items, len)?.end(suffix)
+            }
+            State::Failed => Err(fmt::Error),
+        }
+    }
+}
+
+/// An item of a container laid out to a line width, which was written to the line.
+enum LineItem {
+    /// The item is displayed the same on its own line, so is written from this range of the line.
+    Flat(Range<usize>),
+    /// The item contains containers, rendered as they are displayed on its own line.
+    Rendered(String),
+}
+
+/// Start writing a container which doesn't fit on the line, with each item on its own line,
+/// writing the items which were already written to the line.
+fn break_line<'a, 'b>(
+    f: &'a mut fmt::Formatter<'b>,
+    style: &ContainerStyle,
+    line: &LineWriter,
+    items: &[LineItem],
+    len: Len,
+) -> Result<ContainerDisplayHelper<'a, 'b>, fmt::Error> {
+    let mut helper = ContainerDisplayHelper::after_prefix(f, style, len)?;
+    for item in items {
+        match item {
+            LineItem::Flat(range) => helper.rendered_item(&line.as_str()[range.clone()])?,
+            LineItem::Rendered(item) => helper.rendered_item(item)?,
+        }
+    }
+    Ok(helper)
+}
+
+/// Render an item as it is displayed on its own line, to be written later with `rendered_item`.
+fn render_item(
+    style: &ContainerStyle,
+    flags: Flags,
+    v: &dyn Display,
+) -> Result<String, fmt::Error> {
+    // The item isn't written to the output yet, so mustn't move the column.
+    let _saved = layout::save();
+    limits::buffered(|w| write_item(style.indent, true, w, |w| flags.write(w, v)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+
+    use crate::*;
+
+    /// Displays the items with both `ContainerWriter` and `fmt_container_with_style`.
+    struct Both {
+        style: ContainerStyle,
+        items: Vec<Vec<u32>>,
+        len: bool,
+    }
+
+    impl Both {
+        fn writer(&self) -> impl fmt::Display + '_ {
+            struct Writer<'a>(&'a Both);
+            impl fmt::Display for Writer<'_> {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    let both = self.0;
+                    let len = both.len.then_some(both.items.len());
+                    let mut writer = ContainerWriter::begin_with_style(f, &both.style, "[", len)?;
+                    for item in &both.items {
+                        writer.item(display_container("(", ")", item))?;
+                    }
+                    writer.end("]")
+                }
+            }
+            Writer(self)
+        }
+
+        fn container(&self) -> impl fmt::Display + '_ {
+            struct Container<'a>(&'a Both);
+            impl fmt::Display for Container<'_> {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    let both = self.0;
+                    fmt_container_with_style(
+                        f,
+                        &both.style,
+                        "[",
+                        "]",
+                        both.items
+                            .iter()
+                            .map(|item| display_container("(", ")", item)),
+                    )
+                }
+            }
+            Container(self)
+        }
+    }
+
+    #[test]
+    fn test_writer() {
+        let styles = [
+            ContainerStyle::new(),
+            ContainerStyle::new().break_mode(BreakMode::Always),
+            ContainerStyle::new().break_mode(BreakMode::IfWiderThan(12)),
+        ];
+        let limits = [
+            ContainerLimits::new(),
+            ContainerLimits::new().max_items(1),
+            ContainerLimits::new().max_depth(1),
+            ContainerLimits::new().max_len(10),
+        ];
+        for style in styles {
+            for items in [
+                vec![],
+                vec![vec![1]],
+                vec![vec![1, 2], vec![3]],
+                vec![vec![1, 2, 3]; 3],
+            ] {
+                for len in [false, true] {
+                    let both = Both {
+                        style,
+                        items: items.clone(),
+                        len,
+                    };
+                    for limits in limits {
+                        for alternate in [false, true] {
+                            let (writer, container) = if alternate {
+                                (
+                                    format!("{:#}", display_with_limits(both.writer(), limits)),
+                                    format!("{:#}", display_with_limits(both.container(), limits)),
+                                )
+                            } else {
+                                (
+                                    format!("{}", display_with_limits(both.writer(), limits)),
+                                    format!("{}", display_with_limits(both.container(), limits)),
+                                )
+                            };
+                            assert_eq!(container, writer, "{style:?} {items:?} {limits:?}");
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_writer_flags() {
+        struct Floats;
+        impl fmt::Display for Floats {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let mut writer = ContainerWriter::begin(f, "[", None)?;
+                writer.item(1.0)?;
+                writer.end("]")
+            }
+        }
+        assert_eq!("[1.00]", format!("{:.2}", Floats));
+        assert_eq!("[ 1.00 ]", format!("{:#.2}", Floats));
+    }
+
+    #[test]
+    fn test_writer_wrong_len() {
+        struct Wrong(usize, Vec<u32>);
+        impl fmt::Display for Wrong {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let mut writer = ContainerWriter::begin(f, "[", Some(self.0))?;
+                for x in &self.1 {
+                    writer.item(x)?;
+                }
+                writer.end("]")
+            }
+        }
+        // All the items are displayed, laid out as if there were the given number.
+        assert_eq!("[1, 2]", format!("{:#}", Wrong(0, vec![1, 2])));
+        assert_eq!("[ 1, 2 ]", format!("{:#}", Wrong(1, vec![1, 2])));
+        assert_eq!("[\n  1\n]", format!("{:#}", Wrong(2, vec![1])));
+        assert_eq!("[1, 2]", format!("{}", Wrong(1, vec![1, 2])));
+    }
+
+    #[test]
+    fn test_writer_fitting() {
+        use std::cell::Cell;
+
+        struct Counted<'a>(&'a Cell<usize>);
+        impl fmt::Display for Counted<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.set(self.0.get() + 1);
+                f.write_str("x")
+            }
+        }
+        struct Fitting<'a>(usize, &'a Cell<usize>);
+        impl fmt::Display for Fitting<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let style = ContainerStyle::new().break_mode(BreakMode::IfWiderThan(self.0));
+                let mut writer = ContainerWriter::begin_with_style(f, &style, "[", None)?;
+                for _ in 0..3 {
+                    writer.item(Counted(self.1))?;
+                }
+                writer.end("]")
+            }
+        }
+
+        // Items without containers are only displayed to the line, and written from it.
+        let count = Cell::new(0);
+        assert_eq!("[x, x, x]", format!("{:#}", Fitting(20, &count)));
+        assert_eq!(count.get(), 3);
+        // The item which doesn't fit is displayed again on its own line.
+        let count = Cell::new(0);
+        assert_eq!("[\n  x,\n  x,\n  x\n]", format!("{:#}", Fitting(4, &count)));
+        assert_eq!(count.get(), 4);
+    }
+
+    #[test]
+    fn test_writer_first_item() {
+        use std::cell::Cell;
+
+        /// Counts how many times it is displayed.
+        struct Counted<'a>(&'a Cell<usize>);
+        impl fmt::Display for Counted<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.set(self.0.get() + 1);
+                f.write_str("x")
+            }
+        }
+        struct Buffered<T>(Vec<(&'static str, T)>);
+        impl<T: fmt::Display> fmt::Display for Buffered<T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let mut writer = ContainerWriter::begin(f, "{", None)?;
+                for (k, v) in &self.0 {
+                    writer.keyed_item(k, ": ", v)?;
+                }
+                writer.end("}")
+            }
+        }
+
+        // The buffered first item stops being displayed at the limit.
+        let count = Cell::new(0);
+        let items: Vec<_> = (0..1000).map(|_| Counted(&count)).collect();
+        let items = display_container("[", "]", &items);
+        let buffered = Buffered(vec![("a", &items)]);
+        assert_eq!(
+            "{ a: [\n  x,\n...",
+            format!(
+                "{:#}",
+                display_with_limits(&buffered, ContainerLimits::new().max_len(12))
+            )
+        );
+        assert!(count.get() < 10, "displayed {} items", count.get());
+
+        // Containers laid out to a line width in the first item start from its column.
+        struct List(Vec<u32>);
+        impl fmt::Display for List {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let style = ContainerStyle::new().break_mode(BreakMode::IfWiderThan(14));
+                fmt_container_with_style(f, &style, "[", "]", &self.0)
+            }
+        }
+        let buffered = Buffered(vec![
+            ("long_key", List(vec![1, 2])),
+            ("b", List(vec![1, 2])),
+        ]);
+        assert_eq!(
+            "{\n  long_key: [\n    1,\n    2\n  ],\n  b: [1, 2]\n}",
+            format!("{:#}", buffered)
+        );
+    }
+}
@@ -0,0 +1,204 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+use core::cell::Cell;
+use core::fmt;
+use core::fmt::Debug;
+
+use crate::Dupe;
+
+/// Like [`Cell`], but [`get`](DupeCell::get) requires [`Dupe`] rather than [`Copy`].
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use dupe::DupeCell;
+///
+/// let cell = DupeCell::new(Arc::new("old"));
+/// let old = cell.get();
+/// cell.set(Arc::new("new"));
+/// assert_eq!(*old, "old");
+/// assert_eq!(*cell.get(), "new");
+/// ```
+///
+/// The value is moved out of the cell while it is being duped, so a `clone` implementation
+/// which accesses the cell can't free the value being cloned. Calling `set` from within `clone`
+/// works as expected, but the other accessors panic.
+pub struct DupeCell<T> {
+    // Only `None` while `get` is duping the value.
+    value: Cell<Option<T>>,
+}
+
+impl<T> DupeCell<T> {
+    /// Create a new cell containing the given value.
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        DupeCell {
+            value: Cell::new(Some(value)),
+        }
+    }
+
+    /// Set the contained value, dropping the old one.
+    #[inline]
+    pub fn set(&self, value: T) {
+        drop(self.value.replace(Some(value)));
+    }
+
+    /// Replace the contained value, returning the old one.
+    #[inline]
+    pub fn replace(&self, value: T) -> T {
+        match self.value.replace(Some(value)) {
+            Some(old) => old,
+            None => re_entrant(),
+        }
+    }
+
+    /// Get a mutable reference to the contained value.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        match self.value.get_mut() {
+            Some(value) => value,
+            None => re_entrant(),
+        }
+    }
+
+    /// Consume the cell, returning the contained value.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        match self.value.into_inner() {
+            Some(value) => value,
+            None => re_entrant(),
+        }
+    }
+}
+
+impl<T: Dupe> DupeCell<T> {
+    /// Get a dupe of the contained value.
+    #[inline]
+    pub fn get(&self) -> T {
+        let value = match self.value.take() {
+            Some(value) => value,
+            None => re_entrant(),
+        };
+        let restore = Restore {
+            cell: &self.value,
+            value: Some(value),
+        };
+        restore.value.as_ref().unwrap().dupe()
+    }
+}
+
+impl<T: Default> DupeCell<T> {
+    /// Take the contained value, leaving `Default::default()` in its place.
+    #[inline]
+    pub fn take(&self) -> T {
+        self.replace(T::default())
+    }
+}
+
+/// Puts the value back in the cell, even if `dupe` panics.
+struct Restore<'a, T> {
+    cell: &'a Cell<Option<T>>,
+    value: Option<T>,
+}
+
+impl<T> Drop for Restore<'_, T> {
+    fn drop(&mut self) {
+        // If `dupe` re-entrantly set a value, that is newer than ours, so keep it.
+        let current = self.cell.take();
+        self.cell.set(current.or(self.value.take()));
+    }
+}
+
+#[cold]
+fn re_entrant() -> ! {
+    panic!("`DupeCell` accessed re-entrantly while duping its value")
+}
+
+impl<T: Dupe> Clone for DupeCell<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        DupeCell::new(self.get())
+    }
+}
+
+impl<T: Dupe> Dupe for DupeCell<T> {}
+
+impl<T: Default> Default for DupeCell<T> {
+    #[inline]
+    fn default() -> Self {
+        DupeCell::new(T::default())
+    }
+}
+
+impl<T> From<T> for DupeCell<T> {
+    #[inline]
+    fn from(value: T) -> Self {
+        DupeCell::new(value)
+    }
+}
+
+impl<T: Dupe + Debug> Debug for DupeCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DupeCell")
+            .field("value", &self.get())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[test]
+    fn test_dupe_cell() {
+        let cell = DupeCell::new(Rc::new(1));
+        let x = cell.get();
+        assert_eq!(Rc::strong_count(&x), 2);
+        assert_eq!(*cell.replace(Rc::new(2)), 1);
+        assert_eq!(Rc::strong_count(&x), 1);
+        cell.set(Rc::new(3));
+        assert_eq!(*cell.get(), 3);
+        assert_eq!(*cell.into_inner(), 3);
+    }
+
+    #[test]
+    fn test_dupe_cell_take() {
+        let mut cell = DupeCell::new(Some(Rc::new("test")));
+        assert_eq!(cell.take().as_deref(), Some(&"test"));
+        assert_eq!(cell.get(), None);
+        *cell.get_mut() = Some(Rc::new("again"));
+        assert_eq!(format!("{cell:?}"), "DupeCell { value: Some(\"again\") }");
+    }
+
+    thread_local! {
+        static CELL: DupeCell<ReEntrant> = const { DupeCell::new(ReEntrant) };
+    }
+
+    struct ReEntrant;
+
+    impl Clone for ReEntrant {
+        fn clone(&self) -> Self {
+            CELL.with(|c| c.get())
+        }
+    }
+
+    impl Dupe for ReEntrant {}
+
+    #[test]
+    fn test_dupe_cell_re_entrant() {
+        let res = std::panic::catch_unwind(|| CELL.with(|c| c.get()));
+        assert!(res.is_err());
+        // The value is restored after the panic.
+        CELL.with(|c| assert!(matches!(c.replace(ReEntrant), ReEntrant)));
+    }
+}
@@ -0,0 +1,131 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+/// Dupe a list of bindings, then produce a `move` closure or `async move` block which
+/// captures the dupes rather than the originals.
+///
+/// Each entry is either a variable name, which is shadowed by its dupe, or `expr as name`,
+/// which binds a dupe of `expr` to `name`. Every entry must implement [`Dupe`](crate::Dupe),
+/// so an accidental deep clone is a compile error.
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use dupe::dupe_move;
+///
+/// struct Config {
+///     name: Arc<String>,
+/// }
+///
+/// let a = Arc::new(1);
+/// let c = Config {
+///     name: Arc::new("test".to_owned()),
+/// };
+/// let f = dupe_move!([a, c.name as name] move || format!("{name}={a}"));
+/// assert_eq!(f(), "test=1");
+/// // The originals are still usable.
+/// assert_eq!(*a, 1);
+/// assert_eq!(*c.name, "test");
+/// ```
+#[macro_export]
+macro_rules! dupe_move {
+    // All entries bound, emit the body.
+    (@items [$($acc:tt)*] [] $($body:tt)*) => {
+        {
+            $($acc)*
+            $($body)*
+        }
+    };
+    // A plain variable, shadowed by its dupe.
+    (@items [$($acc:tt)*] [$name:ident $(, $($rest:tt)*)?] $($body:tt)*) => {
+        $crate::dupe_move!(
+            @items
+            [$($acc)* let $name = $crate::Dupe::dupe(&$name);]
+            [$($($rest)*)?]
+            $($body)*
+        )
+    };
+    // An `expr as name` entry, collect the expression up to the `as`.
+    (@items [$($acc:tt)*] [$($rest:tt)+] $($body:tt)*) => {
+        $crate::dupe_move!(@expr [$($acc)*] [] [$($rest)+] $($body)*)
+    };
+    (@expr [$($acc:tt)*] [$($expr:tt)+] [as $name:ident $(, $($rest:tt)*)?] $($body:tt)*) => {
+        $crate::dupe_move!(
+            @items
+            [$($acc)* let $name = $crate::Dupe::dupe(&($($expr)+));]
+            [$($($rest)*)?]
+            $($body)*
+        )
+    };
+    (@expr [$($acc:tt)*] [$($expr:tt)*] [$next:tt $($rest:tt)*] $($body:tt)*) => {
+        $crate::dupe_move!(@expr [$($acc)*] [$($expr)* $next] [$($rest)*] $($body)*)
+    };
+    ([$($items:tt)*] $($body:tt)+) => {
+        $crate::dupe_move!(@items [] [$($items)*] $($body)+)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::pin;
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::task::Context;
+    use std::task::Poll;
+    use std::task::Waker;
+
+    #[test]
+    fn test_dupe_move_closure() {
+        let a = Arc::new(1);
+        let b = Rc::new("b");
+        let f = dupe_move!([a, b,] move || format!("{a}{b}"));
+        assert_eq!(f(), "1b");
+        assert_eq!(Arc::strong_count(&a), 2);
+        drop(f);
+        assert_eq!(Arc::strong_count(&a), 1);
+    }
+
+    #[test]
+    fn test_dupe_move_expr() {
+        struct Foo {
+            field: Arc<String>,
+            nested: (usize, Arc<usize>),
+        }
+        let foo = Foo {
+            field: Arc::new("field".to_owned()),
+            nested: (1, Arc::new(2)),
+        };
+        let f = dupe_move!(
+            [foo.field as field, foo.nested.1 as nested]
+            move || format!("{field}{nested}")
+        );
+        assert_eq!(f(), "field2");
+        assert_eq!(Arc::strong_count(&foo.field), 2);
+        assert_eq!(foo.nested.0, 1);
+    }
+
+    #[test]
+    fn test_dupe_move_thread() {
+        let a = Arc::new(1);
+        let handle = std::thread::spawn(dupe_move!([a] move || *a + 1));
+        assert_eq!(handle.join().unwrap(), 2);
+        assert_eq!(*a, 1);
+    }
+
+    #[test]
+    fn test_dupe_move_async() {
+        let a = Arc::new(1);
+        let fut = pin!(dupe_move!([a as b] async move { *b + 1 }));
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(fut.poll(&mut cx), Poll::Ready(2));
+        assert_eq!(*a, 1);
+    }
+}
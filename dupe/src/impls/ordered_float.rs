@@ -0,0 +1,16 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+#![cfg(feature = "ordered-float")]
+
+use crate::Dupe;
+
+impl<T: Dupe> Dupe for ordered_float::OrderedFloat<T> {}
+impl<T: Dupe> Dupe for ordered_float::NotNan<T> {}
@@ -8,7 +8,8 @@
  * above-listed licenses.
  */
 
-use std::iter::Cloned;
+use core::iter::Cloned;
+use core::iter::FusedIterator;
 
 use crate::Dupe;
 
@@ -41,3 +42,106 @@ where
         self.cloned()
     }
 }
+
+/// Extension traits on iterators over pairs of refs, such as `HashMap::iter`.
+pub trait IterPairDupedExt: Sized {
+    /// Like [`IterDupedExt::duped`], but dupes both components of each pair.
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use std::rc::Rc;
+    ///
+    /// use dupe::IterPairDupedExt;
+    /// let map = BTreeMap::from([(1, Rc::new("one")), (2, Rc::new("two"))]);
+    /// let pairs = map.iter().duped().collect::<Vec<_>>();
+    /// assert_eq!(pairs, vec![(1, Rc::new("one")), (2, Rc::new("two"))]);
+    /// ```
+    fn duped(self) -> DupedPairs<Self>;
+}
+
+impl<'a, I, K, V> IterPairDupedExt for I
+where
+    I: Iterator<Item = (&'a K, &'a V)>,
+    K: 'a + Dupe,
+    V: 'a + Dupe,
+{
+    fn duped(self) -> DupedPairs<Self> {
+        DupedPairs { iter: self }
+    }
+}
+
+/// An iterator which dupes both components of each pair of refs.
+///
+/// Created by [`IterPairDupedExt::duped`].
+#[derive(Clone, Debug)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct DupedPairs<I> {
+    iter: I,
+}
+
+impl<'a, I, K, V> Iterator for DupedPairs<I>
+where
+    I: Iterator<Item = (&'a K, &'a V)>,
+    K: 'a + Dupe,
+    V: 'a + Dupe,
+{
+    type Item = (K, V);
+
+    #[inline]
+    fn next(&mut self) -> Option<(K, V)> {
+        self.iter.next().map(|(k, v)| (k.dupe(), v.dupe()))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a, I, K, V> DoubleEndedIterator for DupedPairs<I>
+where
+    I: DoubleEndedIterator<Item = (&'a K, &'a V)>,
+    K: 'a + Dupe,
+    V: 'a + Dupe,
+{
+    #[inline]
+    fn next_back(&mut self) -> Option<(K, V)> {
+        self.iter.next_back().map(|(k, v)| (k.dupe(), v.dupe()))
+    }
+}
+
+impl<'a, I, K, V> ExactSizeIterator for DupedPairs<I>
+where
+    I: ExactSizeIterator<Item = (&'a K, &'a V)>,
+    K: 'a + Dupe,
+    V: 'a + Dupe,
+{
+}
+
+impl<'a, I, K, V> FusedIterator for DupedPairs<I>
+where
+    I: FusedIterator<Item = (&'a K, &'a V)>,
+    K: 'a + Dupe,
+    V: 'a + Dupe,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[test]
+    fn test_duped_pairs() {
+        let map = HashMap::from([(Rc::new("a"), Rc::new(1))]);
+        let pairs: Vec<_> = map.iter().duped().collect();
+        assert_eq!(pairs, vec![(Rc::new("a"), Rc::new(1))]);
+        assert_eq!(Rc::strong_count(&pairs[0].1), 2);
+        assert_eq!(map.iter().duped().len(), 1);
+        // Iterators over plain refs still pick up `IterDupedExt`.
+        let values: Vec<_> = map.values().duped().collect();
+        assert_eq!(values, vec![Rc::new(1)]);
+    }
+}
@@ -9,28 +9,63 @@
  */
 
 //! A cheap version of [`Clone`].
+//!
+//! The crate is `no_std` compatible when the default `std` feature is disabled, in which case
+//! only the implementations for `core` and `alloc` types are available.
+//!
+//! Implementations for cheaply cloned types from other crates are available behind features
+//! named after the crate: `arcstr`, `either`, `ordered-float`, `triomphe` and `uuid`.
+//! There is no implementation for `bytes::Bytes`, as cloning a `Bytes` created from a `Vec`
+//! allocates.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 
 pub mod __macro_refs;
+pub(crate) mod cell;
+pub(crate) mod dupe_move;
+mod impls;
 pub(crate) mod iter;
 pub(crate) mod option;
+pub(crate) mod result;
+#[cfg(feature = "std")]
+pub(crate) mod rw_lock;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub(crate) mod tuple;
 
-use std::cell::Cell;
-use std::mem::ManuallyDrop;
-use std::num::*;
-use std::rc::Rc;
-use std::sync::Arc;
+use alloc::rc::Rc;
+#[cfg(target_has_atomic = "ptr")]
+use alloc::sync::Arc;
+use core::cell::Cell;
+use core::mem::ManuallyDrop;
+use core::num::*;
 
 pub use dupe_derive::Clone_;
 pub use dupe_derive::Copy_;
 pub use dupe_derive::Dupe;
 pub use dupe_derive::Dupe_;
+pub use dupe_derive::DupeClone;
 
+pub use crate::cell::DupeCell;
+pub use crate::iter::DupedPairs;
 pub use crate::iter::IterDupedExt;
+pub use crate::iter::IterPairDupedExt;
 pub use crate::option::OptionDupedExt;
+pub use crate::result::ResultDupedExt;
+#[cfg(feature = "std")]
+pub use crate::rw_lock::DupeRwLock;
+pub use crate::tuple::TupleDupedExt;
 
 /// Like [`Clone`], but should only be available if [`Clone`] is
 /// constant time and zero allocation (e.g. a few [`Arc`] bumps).
 /// The implementation of `dupe` should _always_ call `clone`.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not cheap to clone",
+    label = "`{Self}` does not implement `Dupe`",
+    note = "consider wrapping it in `Arc`"
+)]
 pub trait Dupe: Clone {
     #[inline]
     fn dupe(&self) -> Self {
@@ -42,20 +77,22 @@ pub trait Dupe: Clone {
 impl<A: ?Sized> Dupe for &A {}
 impl<A: ?Sized> Dupe for *const A {}
 impl<A: ?Sized> Dupe for *mut A {}
+#[cfg(target_has_atomic = "ptr")]
 impl<A: ?Sized> Dupe for Arc<A> {}
-impl<A: ?Sized> Dupe for std::sync::Weak<A> {}
+#[cfg(target_has_atomic = "ptr")]
+impl<A: ?Sized> Dupe for alloc::sync::Weak<A> {}
 impl<A: ?Sized> Dupe for Rc<A> {}
-impl<A: ?Sized> Dupe for std::rc::Weak<A> {}
+impl<A: ?Sized> Dupe for alloc::rc::Weak<A> {}
 impl<A: Copy> Dupe for Cell<A> {}
 impl<A: Dupe> Dupe for ManuallyDrop<A> {}
 
 // Small containers
 impl<A: Dupe> Dupe for Option<A> {}
 impl<T: Dupe, E: Dupe> Dupe for Result<T, E> {}
-impl<A: Dupe> Dupe for std::ops::Bound<A> {}
-impl<A: Dupe> Dupe for std::pin::Pin<A> {}
-impl<A: Dupe> Dupe for std::ptr::NonNull<A> {}
-impl<A: Dupe> Dupe for std::task::Poll<A> {}
+impl<A: Dupe> Dupe for core::ops::Bound<A> {}
+impl<A: Dupe> Dupe for core::pin::Pin<A> {}
+impl<A: Dupe> Dupe for core::ptr::NonNull<A> {}
+impl<A: Dupe> Dupe for core::task::Poll<A> {}
 impl Dupe for () {}
 impl<A: Dupe> Dupe for (A,) {}
 impl<A: Dupe, B: Dupe> Dupe for (A, B) {}
@@ -79,73 +116,154 @@ impl<A: Dupe, B: Dupe, C: Dupe, D: Dupe, E: Dupe, F: Dupe, G: Dupe, H: Dupe, I:
 
 impl<A: Dupe, const N: usize> Dupe for [A; N] {}
 
+/// Implement `Dupe` for each of the listed types.
+macro_rules! impl_dupe {
+    ($($t:ty),* $(,)?) => {
+        $(impl Dupe for $t {})*
+    };
+}
+
 // Atomic types
-impl Dupe for bool {}
-impl Dupe for char {}
-impl Dupe for u8 {}
-impl Dupe for u16 {}
-impl Dupe for u32 {}
-impl Dupe for u64 {}
-impl Dupe for u128 {}
-impl Dupe for usize {}
-impl Dupe for i8 {}
-impl Dupe for i16 {}
-impl Dupe for i32 {}
-impl Dupe for i64 {}
-impl Dupe for i128 {}
-impl Dupe for isize {}
-impl Dupe for f32 {}
-impl Dupe for f64 {}
-impl Dupe for NonZeroU8 {}
-impl Dupe for NonZeroU16 {}
-impl Dupe for NonZeroU32 {}
-impl Dupe for NonZeroU64 {}
-impl Dupe for NonZeroU128 {}
-impl Dupe for NonZeroUsize {}
-impl Dupe for NonZeroI8 {}
-impl Dupe for NonZeroI16 {}
-impl Dupe for NonZeroI32 {}
-impl Dupe for NonZeroI64 {}
-impl Dupe for NonZeroI128 {}
-impl Dupe for NonZeroIsize {}
+impl_dupe!(
+    bool, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64,
+);
+impl_dupe!(
+    NonZeroU8,
+    NonZeroU16,
+    NonZeroU32,
+    NonZeroU64,
+    NonZeroU128,
+    NonZeroUsize,
+    NonZeroI8,
+    NonZeroI16,
+    NonZeroI32,
+    NonZeroI64,
+    NonZeroI128,
+    NonZeroIsize,
+);
+
+// Other core types that are Copyable
+impl_dupe!(
+    core::any::TypeId,
+    core::cmp::Ordering,
+    core::fmt::Alignment,
+    core::marker::PhantomPinned,
+    core::net::IpAddr,
+    core::net::Ipv4Addr,
+    core::net::Ipv6Addr,
+    core::net::SocketAddr,
+    core::net::SocketAddrV4,
+    core::net::SocketAddrV6,
+    core::num::FpCategory,
+    core::num::IntErrorKind,
+    core::ops::RangeFull,
+    core::sync::atomic::Ordering,
+    core::time::Duration,
+);
+impl<T: ?Sized> Dupe for core::marker::PhantomData<T> {}
+impl<A: Dupe> Dupe for core::cmp::Reverse<A> {}
+impl<A: Dupe> Dupe for core::num::Wrapping<A> {}
+impl<B: Dupe, C: Dupe> Dupe for core::ops::ControlFlow<B, C> {}
+impl<A: Dupe> Dupe for core::ops::Range<A> {}
+impl<A: Dupe> Dupe for core::ops::RangeFrom<A> {}
+impl<A: Dupe> Dupe for core::ops::RangeInclusive<A> {}
+impl<A: Dupe> Dupe for core::ops::RangeTo<A> {}
+impl<A: Dupe> Dupe for core::ops::RangeToInclusive<A> {}
+
+// Other core types that are cheap to clone
+impl Dupe for core::task::Waker {}
 
 // Other std types that are Copyable
-impl Dupe for std::any::TypeId {}
-impl Dupe for std::marker::PhantomPinned {}
-impl Dupe for std::net::Ipv4Addr {}
-impl Dupe for std::net::Ipv6Addr {}
-impl Dupe for std::net::SocketAddrV4 {}
-impl Dupe for std::net::SocketAddrV6 {}
-impl Dupe for std::thread::ThreadId {}
-impl Dupe for std::time::Instant {}
-impl Dupe for std::time::SystemTime {}
-impl Dupe for std::time::Duration {}
-impl<T: ?Sized> Dupe for std::marker::PhantomData<T> {}
-
-impl<R> Dupe for fn() -> R {}
-impl<A1, R> Dupe for fn(A1) -> R {}
-impl<A1, A2, R> Dupe for fn(A1, A2) -> R {}
-impl<A1, A2, A3, R> Dupe for fn(A1, A2, A3) -> R {}
-impl<A1, A2, A3, A4, R> Dupe for fn(A1, A2, A3, A4) -> R {}
-impl<A1, A2, A3, A4, A5, R> Dupe for fn(A1, A2, A3, A4, A5) -> R {}
-impl<A1, A2, A3, A4, A5, A6, R> Dupe for fn(A1, A2, A3, A4, A5, A6) -> R {}
-impl<A1, A2, A3, A4, A5, A6, A7, R> Dupe for fn(A1, A2, A3, A4, A5, A6, A7) -> R {}
-impl<A1, A2, A3, A4, A5, A6, A7, A8, R> Dupe for fn(A1, A2, A3, A4, A5, A6, A7, A8) -> R {}
-impl<A1, A2, A3, A4, A5, A6, A7, A8, A9, R> Dupe for fn(A1, A2, A3, A4, A5, A6, A7, A8, A9) -> R {}
-impl<A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, R> Dupe
-    for fn(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10) -> R
-{
-}
-impl<A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, R> Dupe
-    for fn(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11) -> R
-{
+#[cfg(feature = "std")]
+impl_dupe!(
+    std::io::ErrorKind,
+    std::net::Shutdown,
+    std::thread::ThreadId,
+    std::time::Instant,
+    std::time::SystemTime,
+);
+
+// Other std types that are cheap to clone
+#[cfg(feature = "std")]
+impl<T> Dupe for std::sync::mpsc::Sender<T> {}
+#[cfg(feature = "std")]
+impl<T> Dupe for std::sync::mpsc::SyncSender<T> {}
+#[cfg(feature = "std")]
+impl Dupe for std::thread::Thread {}
+
+/// Implement `Dupe` for function pointers with the given arguments, for every safety and
+/// common ABI.
+macro_rules! impl_dupe_fn_abis {
+    ([$($lt:lifetime)*] [$($param:tt)*] [$($arg:ty),*]) => {
+        impl<$($param)* R> Dupe for for<$($lt),*> fn($($arg),*) -> R {}
+        impl<$($param)* R> Dupe for for<$($lt),*> unsafe fn($($arg),*) -> R {}
+        impl<$($param)* R> Dupe for for<$($lt),*> extern "C" fn($($arg),*) -> R {}
+        impl<$($param)* R> Dupe for for<$($lt),*> unsafe extern "C" fn($($arg),*) -> R {}
+        impl<$($param)* R> Dupe for for<$($lt),*> extern "system" fn($($arg),*) -> R {}
+        impl<$($param)* R> Dupe for for<$($lt),*> unsafe extern "system" fn($($arg),*) -> R {}
+    };
 }
-impl<A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, R> Dupe
-    for fn(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12) -> R
-{
+
+/// Implement `Dupe` for function pointers where each argument is taken by value.
+macro_rules! impl_dupe_fn {
+    ($($a:ident)*) => {
+        impl_dupe_fn_abis!([] [$($a,)*] [$($a),*]);
+    };
 }
+
+impl_dupe_fn!();
+impl_dupe_fn!(A1);
+impl_dupe_fn!(A1 A2);
+impl_dupe_fn!(A1 A2 A3);
+impl_dupe_fn!(A1 A2 A3 A4);
+impl_dupe_fn!(A1 A2 A3 A4 A5);
+impl_dupe_fn!(A1 A2 A3 A4 A5 A6);
+impl_dupe_fn!(A1 A2 A3 A4 A5 A6 A7);
+impl_dupe_fn!(A1 A2 A3 A4 A5 A6 A7 A8);
+impl_dupe_fn!(A1 A2 A3 A4 A5 A6 A7 A8 A9);
+impl_dupe_fn!(A1 A2 A3 A4 A5 A6 A7 A8 A9 A10);
+impl_dupe_fn!(A1 A2 A3 A4 A5 A6 A7 A8 A9 A10 A11);
+impl_dupe_fn!(A1 A2 A3 A4 A5 A6 A7 A8 A9 A10 A11 A12);
 // Rust goes up to 12 arguments for traits, so we follow
 
+/// Function pointers taking arguments by reference, like `fn(&A)`, are higher-ranked, so need
+/// their own implementations.
+///
+/// These overlap with the by-value implementations (`fn(A)` with `A = &'x A`) according to the
+/// `coherence_leak_check` future-compatibility lint. The compiler currently accepts them, but
+/// may reject them in future, so they are opt-in behind the `higher_ranked_fn` feature.
+// TODO: Remove the feature, or these implementations, once rust-lang/rust#56105 is resolved.
+#[cfg(feature = "higher_ranked_fn")]
+mod higher_ranked_fn {
+    #![allow(coherence_leak_check)]
+
+    use crate::Dupe;
+
+    /// Implement `Dupe` for function pointers where each argument is taken by value, by `&`
+    /// or by `&mut`, giving every combination with at least one reference.
+    macro_rules! impl_dupe_fn_refs {
+        // All by value, which is implemented outside this module.
+        ([] [$($param:tt)*] [$($arg:ty,)*] []) => {};
+        ([$($lt:lifetime)+] [$($param:tt)*] [$($arg:ty,)*] []) => {
+            impl_dupe_fn_abis!([$($lt)*] [$($param)*] [$($arg),*]);
+        };
+        ([$($lt:lifetime)*] [$($param:tt)*] [$($arg:ty,)*] [$a:ident $a_lt:lifetime $($rest:tt)*]) => {
+            impl_dupe_fn_refs!([$($lt)*] [$($param)* $a,] [$($arg,)* $a,] [$($rest)*]);
+            impl_dupe_fn_refs!(
+                [$($lt)* $a_lt] [$($param)* $a: ?Sized,] [$($arg,)* &$a_lt $a,] [$($rest)*]
+            );
+            impl_dupe_fn_refs!(
+                [$($lt)* $a_lt] [$($param)* $a: ?Sized,] [$($arg,)* &$a_lt mut $a,] [$($rest)*]
+            );
+        };
+    }
+
+    // Every combination grows quickly, so we only do that up to 3 arguments.
+    impl_dupe_fn_refs!([] [] [] [A1 'a1]);
+    impl_dupe_fn_refs!([] [] [] [A1 'a1 A2 'a2]);
+    impl_dupe_fn_refs!([] [] [] [A1 'a1 A2 'a2 A3 'a3]);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -196,6 +314,118 @@ mod tests {
         assert_eq!(x, Dupe::dupe(&x));
     }
 
+    #[test]
+    fn test_dupe_field_bounds() {
+        #[derive(Debug, PartialEq, Eq)]
+        struct NoClone();
+        #[derive(Clone_, Dupe, Debug, PartialEq, Eq)]
+        struct Handle<T> {
+            inner: Arc<T>,
+            marker: std::marker::PhantomData<T>,
+        }
+        // Recursive fields fall back to bounding the type parameters, here `T: Dupe`.
+        #[derive(Clone_, Dupe, Debug, PartialEq, Eq)]
+        struct List<T> {
+            head: T,
+            tail: Option<Arc<List<T>>>,
+        }
+        #[derive(Clone, Dupe, Debug, PartialEq, Eq)]
+        struct Cloned<T> {
+            inner: Rc<T>,
+        }
+
+        let x = Handle {
+            inner: Arc::new(NoClone()),
+            marker: std::marker::PhantomData,
+        };
+        assert_eq!(x, Dupe::dupe(&x));
+
+        let x = List {
+            head: 1,
+            tail: Some(Arc::new(List {
+                head: 2,
+                tail: None,
+            })),
+        };
+        assert_eq!(x, Dupe::dupe(&x));
+
+        // `derive(Clone)` still needs `T: Clone`, but not `T: Dupe`.
+        let x = Cloned {
+            inner: Rc::new("test".to_owned()),
+        };
+        assert_eq!(x, Dupe::dupe(&x));
+    }
+
+    #[test]
+    fn test_clone_field_bounds() {
+        #[derive(Debug, PartialEq, Eq)]
+        struct NoClone();
+        #[derive(Clone_, Debug, PartialEq, Eq)]
+        struct Foo<T, U> {
+            value: T,
+            shared: Arc<U>,
+        }
+
+        let x = Foo {
+            value: "test".to_owned(),
+            shared: Arc::new(NoClone()),
+        };
+        assert_eq!(x, x.clone());
+    }
+
+    #[test]
+    fn test_dupe_bound() {
+        #[derive(Debug, PartialEq, Eq)]
+        struct NoClone();
+        #[derive(Clone_, Dupe, Debug, PartialEq, Eq)]
+        #[dupe(bound = "")]
+        struct FooT<T> {
+            foo: Arc<T>,
+        }
+        #[derive(Clone_, Dupe_, Debug, PartialEq, Eq)]
+        #[dupe(bound = "T: Send + Sync")]
+        struct BarT<T> {
+            bar: Arc<T>,
+        }
+
+        let x = FooT {
+            foo: Arc::new(NoClone()),
+        };
+        assert_eq!(x, Dupe::dupe(&x));
+        let x = BarT {
+            bar: Arc::new(NoClone()),
+        };
+        assert_eq!(x, Dupe::dupe(&x));
+    }
+
+    #[test]
+    fn test_dupe_skip_check() {
+        // Cheap to clone, but doesn't implement `Dupe`.
+        #[derive(Clone, Debug, PartialEq, Eq)]
+        struct ForeignArc(Arc<String>);
+        #[derive(Clone, Dupe, Debug, PartialEq, Eq)]
+        struct Foo {
+            #[dupe(skip_check)]
+            foreign: ForeignArc,
+            checked: Arc<String>,
+        }
+        #[derive(Clone, Dupe, Debug, PartialEq, Eq)]
+        enum Bar {
+            Foreign(#[dupe(skip_check)] ForeignArc),
+            Checked(usize),
+        }
+
+        let x = Foo {
+            foreign: ForeignArc(Arc::new("foo".to_owned())),
+            checked: Arc::new("bar".to_owned()),
+        };
+        assert_eq!(x, Dupe::dupe(&x));
+        let x = Bar::Foreign(ForeignArc(Arc::new("foo".to_owned())));
+        assert_eq!(x, Dupe::dupe(&x));
+        let x = Bar::Checked(1);
+        assert_eq!(x, Dupe::dupe(&x));
+    }
+
     #[test]
     fn test_dupe_enum() {
         #[derive(Clone, Dupe, Debug, PartialEq, Eq)]
@@ -230,11 +460,134 @@ mod tests {
         assert_eq!(x, Dupe::dupe(&x));
     }
 
+    #[test]
+    fn test_dupe_clone() {
+        #[derive(DupeClone, Debug, PartialEq, Eq)]
+        struct Handle<T>(Arc<T>, usize);
+        #[derive(DupeClone, Debug, PartialEq, Eq)]
+        enum Either<T> {
+            Left { handle: Handle<T> },
+            Right(Rc<String>),
+        }
+        // A `Vec` is not `Dupe`, but `Handle` still is, as `Arc<Vec<u8>>` is.
+        let x = Handle(Arc::new(vec![1u8]), 3);
+        let y = x.clone();
+        assert_eq!(x, y);
+        assert_eq!(Arc::strong_count(&x.0), 2);
+        let x = Either::Left { handle: y.dupe() };
+        assert_eq!(x.clone(), x);
+        let x = Either::<()>::Right(Rc::new("right".to_owned()));
+        assert_eq!(x.dupe(), x);
+    }
+
+    #[test]
+    fn test_dupe_clone_bound() {
+        #[derive(DupeClone)]
+        #[dupe(bound = "T: Dupe")]
+        struct Wrap<T>(T);
+        fn is_dupe<T: Dupe>() {}
+        is_dupe::<Wrap<Arc<String>>>();
+    }
+
     #[test]
     fn test_dupe_fn()
     where
         fn(usize): Dupe,
         fn(String, Vec<usize>) -> bool: Dupe,
+        fn(&'static str): Dupe,
+        unsafe fn(*const u8) -> u8: Dupe,
+        extern "C" fn(i32) -> i32: Dupe,
+        extern "system" fn(): Dupe,
+        fn(u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8): Dupe,
+        unsafe extern "C" fn(u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8): Dupe,
+    {
+        // Tests are in the where
+    }
+
+    #[cfg(feature = "higher_ranked_fn")]
+    #[test]
+    fn test_dupe_higher_ranked_fn()
+    where
+        fn(&str): Dupe,
+        for<'a> fn(&'a [u8], usize) -> Option<usize>: Dupe,
+        fn(&mut String, &usize, bool): Dupe,
+        unsafe extern "C" fn(&u8): Dupe,
+    {
+        // Tests are in the where
+    }
+
+    #[test]
+    fn test_dupe_std()
+    where
+        std::cmp::Ordering: Dupe,
+        std::cmp::Reverse<usize>: Dupe,
+        std::fmt::Alignment: Dupe,
+        std::io::ErrorKind: Dupe,
+        std::net::IpAddr: Dupe,
+        std::net::Shutdown: Dupe,
+        std::net::SocketAddr: Dupe,
+        std::num::FpCategory: Dupe,
+        std::num::IntErrorKind: Dupe,
+        std::num::Wrapping<u8>: Dupe,
+        std::ops::ControlFlow<usize, Arc<String>>: Dupe,
+        std::ops::Range<usize>: Dupe,
+        std::ops::RangeFrom<usize>: Dupe,
+        std::ops::RangeFull: Dupe,
+        std::ops::RangeInclusive<usize>: Dupe,
+        std::ops::RangeTo<usize>: Dupe,
+        std::ops::RangeToInclusive<usize>: Dupe,
+        std::sync::atomic::Ordering: Dupe,
+        std::sync::mpsc::Sender<String>: Dupe,
+        std::sync::mpsc::SyncSender<String>: Dupe,
+        std::task::Waker: Dupe,
+        std::thread::Thread: Dupe,
+    {
+        // Tests are in the where
+    }
+
+    #[cfg(feature = "arcstr")]
+    #[test]
+    fn test_dupe_arcstr()
+    where
+        arcstr::ArcStr: Dupe,
+    {
+        // Tests are in the where
+    }
+
+    #[cfg(feature = "either")]
+    #[test]
+    fn test_dupe_either()
+    where
+        either::Either<usize, Arc<String>>: Dupe,
+    {
+        // Tests are in the where
+    }
+
+    #[cfg(feature = "ordered-float")]
+    #[test]
+    fn test_dupe_ordered_float()
+    where
+        ordered_float::OrderedFloat<f64>: Dupe,
+        ordered_float::NotNan<f32>: Dupe,
+    {
+        // Tests are in the where
+    }
+
+    #[cfg(feature = "triomphe")]
+    #[test]
+    fn test_dupe_triomphe()
+    where
+        triomphe::Arc<String>: Dupe,
+        triomphe::Arc<str>: Dupe,
+    {
+        // Tests are in the where
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn test_dupe_uuid()
+    where
+        uuid::Uuid: Dupe,
     {
         // Tests are in the where
     }
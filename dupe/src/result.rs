@@ -0,0 +1,49 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+use crate::Dupe;
+
+/// Extension traits on [`Result`](Result) where it holds refs.
+pub trait ResultDupedExt {
+    type Item;
+    type Error;
+
+    /// Like `Option::cloned`, but for both sides of a `Result`, and with a `Dupe` constraint.
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    ///
+    /// use dupe::ResultDupedExt;
+    /// let ok = Rc::new("ok");
+    /// let err = Rc::new("err");
+    /// assert_eq!(Ok::<_, &Rc<&str>>(&ok).duped(), Ok(ok.clone()));
+    /// assert_eq!(Err::<&Rc<&str>, _>(&err).duped(), Err(err.clone()));
+    /// ```
+    fn duped(self) -> Result<Self::Item, Self::Error>
+    where
+        Self::Item: Dupe,
+        Self::Error: Dupe;
+}
+
+impl<T, E> ResultDupedExt for Result<&T, &E> {
+    type Item = T;
+    type Error = E;
+
+    fn duped(self) -> Result<T, E>
+    where
+        T: Dupe,
+        E: Dupe,
+    {
+        match self {
+            Ok(x) => Ok(x.dupe()),
+            Err(e) => Err(e.dupe()),
+        }
+    }
+}
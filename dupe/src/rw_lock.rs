@@ -0,0 +1,150 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+use std::fmt;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::sync::PoisonError;
+use std::sync::RwLock;
+
+use crate::Dupe;
+
+/// A thread-safe slot holding an [`Arc`], guarded by a [`RwLock`], whose
+/// [`load`](DupeRwLock::load) returns a dupe and whose value can be replaced. Useful for values
+/// such as configuration which are read often and occasionally reloaded.
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use dupe::DupeRwLock;
+///
+/// let config = DupeRwLock::new(Arc::new("v1"));
+/// let old = config.load();
+/// config.store(Arc::new("v2"));
+/// assert_eq!(*old, "v1");
+/// assert_eq!(*config.load(), "v2");
+/// ```
+///
+/// Readers and writers take the lock, but only hold it for a reference count update or a
+/// pointer replacement, and old values are dropped after it is released. Nothing can panic
+/// while the lock is held, so it is never poisoned in practice, and poisoning is ignored
+/// rather than being reported by any method.
+pub struct DupeRwLock<T: ?Sized> {
+    value: RwLock<Arc<T>>,
+}
+
+impl<T: ?Sized> DupeRwLock<T> {
+    /// Create a new slot containing the given value.
+    pub const fn new(value: Arc<T>) -> Self {
+        DupeRwLock {
+            value: RwLock::new(value),
+        }
+    }
+
+    /// Get a dupe of the current value.
+    pub fn load(&self) -> Arc<T> {
+        self.value
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .dupe()
+    }
+
+    /// Replace the current value, dropping the old one.
+    pub fn store(&self, value: Arc<T>) {
+        drop(self.swap(value));
+    }
+
+    /// Replace the current value, returning the old one.
+    pub fn swap(&self, value: Arc<T>) -> Arc<T> {
+        let mut guard = self.value.write().unwrap_or_else(PoisonError::into_inner);
+        std::mem::replace(&mut *guard, value)
+    }
+
+    /// Consume the slot, returning the current value.
+    pub fn into_inner(self) -> Arc<T> {
+        self.value
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T> DupeRwLock<T> {
+    /// Create a new slot containing the value, wrapped in an [`Arc`].
+    pub fn from_value(value: T) -> Self {
+        DupeRwLock::new(Arc::new(value))
+    }
+}
+
+impl<T: ?Sized> From<Arc<T>> for DupeRwLock<T> {
+    fn from(value: Arc<T>) -> Self {
+        DupeRwLock::new(value)
+    }
+}
+
+impl<T: Default> Default for DupeRwLock<T> {
+    fn default() -> Self {
+        DupeRwLock::from_value(T::default())
+    }
+}
+
+impl<T: ?Sized + Debug> Debug for DupeRwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DupeRwLock")
+            .field("value", &self.load())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn test_dupe_rw_lock() {
+        let slot = DupeRwLock::from_value(1);
+        let x = slot.load();
+        assert_eq!(Arc::strong_count(&x), 2);
+        assert_eq!(*slot.swap(Arc::new(2)), 1);
+        assert_eq!(Arc::strong_count(&x), 1);
+        slot.store(Arc::new(3));
+        assert_eq!(format!("{slot:?}"), "DupeRwLock { value: 3 }");
+        assert_eq!(*slot.into_inner(), 3);
+    }
+
+    #[test]
+    fn test_dupe_rw_lock_unsized() {
+        let slot: DupeRwLock<str> = DupeRwLock::new(Arc::from("hello"));
+        slot.store(Arc::from("world"));
+        assert_eq!(&*slot.load(), "world");
+    }
+
+    #[test]
+    fn test_dupe_rw_lock_threads() {
+        let slot = DupeRwLock::from_value(0usize);
+        thread::scope(|s| {
+            s.spawn(|| {
+                for i in 1..=100 {
+                    slot.store(Arc::new(i));
+                }
+            });
+            s.spawn(|| {
+                let mut last = 0;
+                for _ in 0..100 {
+                    let x = *slot.load();
+                    assert!(x >= last);
+                    last = x;
+                }
+            });
+        });
+        assert_eq!(*slot.load(), 100);
+    }
+}
@@ -0,0 +1,217 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+//! Test helpers to check that [`Dupe`] is actually cheap, i.e. doesn't allocate or free.
+//!
+//! The checks rely on [`CountingAllocator`] being the global allocator of the test binary:
+//!
+//! ```
+//! use std::sync::Arc;
+//!
+//! use dupe::testing::CountingAllocator;
+//! use dupe::testing::assert_dupe_is_cheap;
+//!
+//! #[global_allocator]
+//! static ALLOCATOR: CountingAllocator = CountingAllocator;
+//!
+//! # fn main() {
+//! assert_dupe_is_cheap(&Arc::new(vec![1, 2, 3]));
+//! # }
+//! ```
+
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+use std::alloc::System;
+use std::cell::Cell;
+use std::hint::black_box;
+
+use crate::Dupe;
+
+// Counters are per thread, so tests running in parallel don't see each other's allocations.
+thread_local! {
+    static ALLOCS: Cell<usize> = const { Cell::new(0) };
+    static FREES: Cell<usize> = const { Cell::new(0) };
+}
+
+fn bump(counter: &'static std::thread::LocalKey<Cell<usize>>) {
+    // Ignore failures, which only happen during thread teardown.
+    let _ = counter.try_with(|c| c.set(c.get() + 1));
+}
+
+fn read(counter: &'static std::thread::LocalKey<Cell<usize>>) -> usize {
+    counter.try_with(|c| c.get()).unwrap_or(0)
+}
+
+/// A global allocator which forwards to [`System`], counting the allocations and frees
+/// made by each thread.
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        bump(&ALLOCS);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        bump(&ALLOCS);
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        bump(&FREES);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        bump(&ALLOCS);
+        bump(&FREES);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+/// Allocations and frees made by the current thread while running a function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocCounts {
+    /// Number of allocations, including reallocations.
+    pub allocs: usize,
+    /// Number of frees, including reallocations.
+    pub frees: usize,
+}
+
+/// Count the allocations and frees made by the current thread while running `f`.
+///
+/// Panics if [`CountingAllocator`] is not the global allocator, as the counts would
+/// always be zero.
+pub fn count_allocations<R>(f: impl FnOnce() -> R) -> (R, AllocCounts) {
+    let allocs = read(&ALLOCS);
+    drop(black_box(Box::new(0u8)));
+    assert!(
+        read(&ALLOCS) != allocs,
+        "`CountingAllocator` must be installed with `#[global_allocator]` to count allocations"
+    );
+
+    let allocs = read(&ALLOCS);
+    let frees = read(&FREES);
+    let res = f();
+    let counts = AllocCounts {
+        allocs: read(&ALLOCS) - allocs,
+        frees: read(&FREES) - frees,
+    };
+    (res, counts)
+}
+
+fn check_dupe_is_cheap<T: Dupe>(value: &T) -> Result<(), String> {
+    let (dupe, counts) = count_allocations(|| black_box(value).dupe());
+    // Dropping the dupe is outside the measurement, it's the `dupe` call we care about.
+    drop(dupe);
+    if counts.allocs == 0 && counts.frees == 0 {
+        Ok(())
+    } else {
+        Err(format!(
+            "`dupe` of `{}` is not cheap: {} allocation(s) and {} free(s)",
+            std::any::type_name::<T>(),
+            counts.allocs,
+            counts.frees
+        ))
+    }
+}
+
+/// Assert that calling `dupe` on `value` neither allocates nor frees memory.
+pub fn assert_dupe_is_cheap<T: Dupe>(value: &T) {
+    if let Err(e) = check_dupe_is_cheap(value) {
+        panic!("{e}");
+    }
+}
+
+/// Run [`assert_dupe_is_cheap`] over `cases` values made by `generate`.
+///
+/// Each case passes a different pseudo-random seed to `generate`. The seeds are deterministic,
+/// and a failure reports the seed, so a failing case can be reproduced.
+pub fn assert_dupe_is_cheap_for_generated<T: Dupe>(
+    cases: usize,
+    mut generate: impl FnMut(u64) -> T,
+) {
+    let mut state = 0u64;
+    for case in 0..cases {
+        let seed = splitmix64(&mut state);
+        if let Err(e) = check_dupe_is_cheap(&generate(seed)) {
+            panic!("case {case} (seed {seed:#x}) failed: {e}");
+        }
+    }
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    #[test]
+    fn test_cheap() {
+        assert_dupe_is_cheap(&1);
+        assert_dupe_is_cheap(&Arc::new(vec![1, 2, 3]));
+        assert_dupe_is_cheap(&Some(Rc::new("test".to_owned())));
+    }
+
+    #[test]
+    fn test_count_allocations() {
+        let (v, counts) = count_allocations(|| vec![1, 2, 3]);
+        assert_eq!(
+            counts,
+            AllocCounts {
+                allocs: 1,
+                frees: 0
+            }
+        );
+        let ((), counts) = count_allocations(|| drop(v));
+        assert_eq!(
+            counts,
+            AllocCounts {
+                allocs: 0,
+                frees: 1
+            }
+        );
+    }
+
+    #[derive(Clone)]
+    struct NotCheap(#[allow(dead_code)] Vec<u8>);
+
+    // Deliberately wrong, to check that we catch it.
+    impl Dupe for NotCheap {}
+
+    #[test]
+    #[should_panic(expected = "is not cheap: 1 allocation(s) and 0 free(s)")]
+    fn test_not_cheap() {
+        assert_dupe_is_cheap(&NotCheap(vec![1]));
+    }
+
+    #[test]
+    fn test_generated() {
+        assert_dupe_is_cheap_for_generated(100, |seed| Arc::new(vec![0u8; (seed % 64) as usize]));
+    }
+
+    #[test]
+    #[should_panic(expected = "case 0 (seed 0xe220a8397b1dcdaf) failed")]
+    fn test_generated_not_cheap() {
+        assert_dupe_is_cheap_for_generated(100, |seed| NotCheap(vec![0; 1 + (seed % 64) as usize]));
+    }
+}
@@ -0,0 +1,46 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+use crate::Dupe;
+
+/// Extension traits on pairs of refs, such as the items of a map iterator.
+pub trait TupleDupedExt {
+    type First;
+    type Second;
+
+    /// Dupe both components of the pair.
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use std::rc::Rc;
+    ///
+    /// use dupe::TupleDupedExt;
+    /// let map = HashMap::from([(1, Rc::new("one"))]);
+    /// let (k, v) = map.iter().next().unwrap().duped();
+    /// assert_eq!((k, v), (1, Rc::new("one")));
+    /// ```
+    fn duped(self) -> (Self::First, Self::Second)
+    where
+        Self::First: Dupe,
+        Self::Second: Dupe;
+}
+
+impl<A, B> TupleDupedExt for (&A, &B) {
+    type First = A;
+    type Second = B;
+
+    fn duped(self) -> (A, B)
+    where
+        A: Dupe,
+        B: Dupe,
+    {
+        (self.0.dupe(), self.1.dupe())
+    }
+}
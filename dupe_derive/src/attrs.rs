@@ -0,0 +1,74 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+use syn::Attribute;
+use syn::Field;
+use syn::LitStr;
+use syn::Token;
+use syn::WherePredicate;
+use syn::punctuated::Punctuated;
+
+/// Attributes placed on the type, e.g. `#[dupe(bound = "T: Dupe")]`.
+#[derive(Default)]
+pub(crate) struct DupeTypeAttrs {
+    /// If set, replaces the bounds that would otherwise be generated.
+    pub(crate) bound: Option<Vec<WherePredicate>>,
+}
+
+/// Attributes placed on a field, e.g. `#[dupe(skip_check)]`.
+#[derive(Default)]
+pub(crate) struct DupeFieldAttrs {
+    /// Don't check that the field type implements `Dupe`.
+    pub(crate) skip_check: bool,
+}
+
+fn is_dupe_attr(attr: &Attribute) -> bool {
+    attr.path().is_ident("dupe")
+}
+
+pub(crate) fn parse_type_attrs(attrs: &[Attribute]) -> syn::Result<DupeTypeAttrs> {
+    let mut res = DupeTypeAttrs::default();
+    for attr in attrs.iter().filter(|a| is_dupe_attr(a)) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("bound") {
+                if res.bound.is_some() {
+                    return Err(meta.error("duplicate `bound` attribute"));
+                }
+                let s: LitStr = meta.value()?.parse()?;
+                let predicates =
+                    s.parse_with(Punctuated::<WherePredicate, Token![,]>::parse_terminated)?;
+                res.bound = Some(predicates.into_iter().collect());
+                Ok(())
+            } else if meta.path.is_ident("skip_check") {
+                Err(meta.error("`skip_check` must be placed on a field, not on the type"))
+            } else {
+                Err(meta.error("unknown `dupe` attribute, expected `bound`"))
+            }
+        })?;
+    }
+    Ok(res)
+}
+
+pub(crate) fn parse_field_attrs(field: &Field) -> syn::Result<DupeFieldAttrs> {
+    let mut res = DupeFieldAttrs::default();
+    for attr in field.attrs.iter().filter(|a| is_dupe_attr(a)) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip_check") {
+                res.skip_check = true;
+                Ok(())
+            } else if meta.path.is_ident("bound") {
+                Err(meta.error("`bound` must be placed on the type, not on a field"))
+            } else {
+                Err(meta.error("unknown `dupe` attribute, expected `skip_check`"))
+            }
+        })?;
+    }
+    Ok(res)
+}
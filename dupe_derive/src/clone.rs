@@ -10,22 +10,33 @@
 
 use quote::quote;
 use syn::DeriveInput;
+use syn::TypeParamBound;
 use syn::parse_macro_input;
+use syn::parse_quote;
 
+use crate::util::add_field_bounds;
 use crate::util::duplicate_impl;
+use crate::util::extract_all_fields;
 
 pub fn derive_clone_(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
-
     let name = &input.ident;
-    let body = duplicate_impl(&input.data, &quote! { ::std::clone::Clone::clone });
+
+    let field_tys = match extract_all_fields(&input.data) {
+        Ok(fields) => fields.map(|f| &f.ty),
+        Err(e) => return e.into_compile_error().into(),
+    };
+    let bound: TypeParamBound = parse_quote!(::core::clone::Clone);
+    let generics = add_field_bounds(input.generics.clone(), name, field_tys, &bound);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let body = duplicate_impl(&input.data, &quote! { ::core::clone::Clone::clone });
     let generated = quote! {
         // Clippy wants us to use Copy if we can - we prefer to be agnostic.
         // Add unknown_lints temporarily.
         #[allow(unknown_lints)]
         #[allow(clippy::incorrect_clone_impl_on_copy_type)]
-        impl #impl_generics ::std::clone::Clone for #name #ty_generics #where_clause {
+        impl #impl_generics ::core::clone::Clone for #name #ty_generics #where_clause {
             fn clone(&self) -> Self {
                 #body
             }
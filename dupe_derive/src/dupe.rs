@@ -10,15 +10,19 @@
 
 use proc_macro2::TokenStream;
 use quote::quote;
+use quote::quote_spanned;
 use syn::DeriveInput;
-use syn::Ident;
-use syn::Type;
+use syn::Field;
 use syn::TypeParamBound;
 use syn::parse_macro_input;
 use syn::parse_quote;
+use syn::spanned::Spanned;
 
-use crate::util::add_trait_bounds;
-use crate::util::extract_all_field_tys;
+use crate::attrs::parse_field_attrs;
+use crate::attrs::parse_type_attrs;
+use crate::util::add_field_bounds;
+use crate::util::duplicate_impl;
+use crate::util::extract_all_fields;
 
 pub fn derive_dupe(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     derive_dupe_explicit(input, true)
@@ -33,50 +37,181 @@ fn derive_dupe_explicit(
     with_traits: bool,
 ) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
+    match derive_dupe_impl(input, with_traits) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.into_compile_error().into(),
+    }
+}
 
+fn derive_dupe_impl(input: DeriveInput, with_traits: bool) -> syn::Result<TokenStream> {
     let name = &input.ident;
+    let attrs = parse_type_attrs(&input.attrs)?;
 
-    // Add a bound `T: Dupe` to every type parameter T.
-    let generics = if with_traits {
-        let bound: TypeParamBound = parse_quote!(dupe::Dupe);
-        add_trait_bounds(input.generics, &bound)
-    } else {
-        input.generics
-    };
-    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let mut checked_fields = Vec::new();
+    for field in extract_all_fields(&input.data)? {
+        if !parse_field_attrs(field)?.skip_check {
+            checked_fields.push(field);
+        }
+    }
 
-    let all_fields = match extract_all_field_tys(&input.data) {
-        Ok(r) => r,
-        Err(e) => {
-            return e.into_compile_error().into();
+    let generics = match attrs.bound {
+        // Explicit bounds replace whatever we would have generated.
+        Some(bound) => {
+            let mut generics = input.generics.clone();
+            generics.make_where_clause().predicates.extend(bound);
+            generics
+        }
+        // Add a bound `Field: Dupe` to every field type that mentions a type parameter.
+        None if with_traits => {
+            let bound: TypeParamBound = parse_quote!(dupe::Dupe);
+            let mut generics = add_field_bounds(
+                input.generics.clone(),
+                name,
+                checked_fields.iter().map(|f| &f.ty),
+                &bound,
+            );
+            if generics.type_params().next().is_some() {
+                // `Clone` may carry bounds of its own (e.g. `derive(Clone)` requires `T: Clone`),
+                // so require it explicitly rather than assuming the field bounds imply it.
+                let (_, ty_generics, _) = input.generics.split_for_impl();
+                generics
+                    .make_where_clause()
+                    .predicates
+                    .push(parse_quote!(#name #ty_generics: ::core::clone::Clone));
+            }
+            generics
         }
+        None => input.generics.clone(),
     };
-    let check_each_field_dupe = check_each_field_dupe(all_fields);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
-    let check_func_name = Ident::new(
-        &format!("__implicit_dupe_check_for_fields_of_{name}"),
-        name.span(),
-    );
+    let check_each_field_dupe = check_each_field_dupe(checked_fields, true);
 
-    let generated = quote! {
+    Ok(quote! {
         impl #impl_generics dupe::Dupe for #name #ty_generics #where_clause {
         }
 
-        #[doc(hidden)]
-        #[allow(non_snake_case)]
-        fn #check_func_name #impl_generics (_x: #name #ty_generics) #where_clause {
-            #check_each_field_dupe
+        // An anonymous const, so the check doesn't add a name to the user's namespace.
+        const _: () = {
+            #[allow(dead_code)]
+            fn check #impl_generics (_x: #name #ty_generics) #where_clause {
+                #check_each_field_dupe
+            }
+        };
+    })
+}
+
+pub fn derive_dupe_clone(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match derive_dupe_clone_impl(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.into_compile_error().into(),
+    }
+}
+
+fn derive_dupe_clone_impl(input: DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let attrs = parse_type_attrs(&input.attrs)?;
+
+    let fields = extract_all_fields(&input.data)?.collect::<Vec<_>>();
+    for field in &fields {
+        if parse_field_attrs(field)?.skip_check {
+            // Every field is duped by `clone`, so there is nothing to skip.
+            return Err(syn::Error::new_spanned(
+                field,
+                "`skip_check` is not supported by `DupeClone`, every field must be `Dupe`",
+            ));
+        }
+    }
+
+    let generics = match attrs.bound {
+        Some(bound) => {
+            let mut generics = input.generics.clone();
+            generics.make_where_clause().predicates.extend(bound);
+            generics
+        }
+        None => {
+            let bound: TypeParamBound = parse_quote!(dupe::Dupe);
+            add_field_bounds(
+                input.generics.clone(),
+                name,
+                fields.iter().map(|f| &f.ty),
+                &bound,
+            )
         }
     };
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
-    generated.into()
+    // The check below requires every field to be `Dupe`, for which `clone` is `dupe`. Cloning
+    // here rather than calling `Dupe::dupe` means a field which isn't `Dupe` is only reported
+    // by the check, at the field.
+    let body = duplicate_impl(&input.data, &quote! { ::core::clone::Clone::clone });
+    let check_each_field_dupe = check_each_field_dupe(fields, false);
+
+    Ok(quote! {
+        #[allow(unknown_lints)]
+        #[allow(clippy::incorrect_clone_impl_on_copy_type)]
+        impl #impl_generics ::core::clone::Clone for #name #ty_generics #where_clause {
+            fn clone(&self) -> Self {
+                #body
+            }
+        }
+
+        impl #impl_generics dupe::Dupe for #name #ty_generics #where_clause {
+        }
+
+        const _: () = {
+            #[allow(dead_code)]
+            fn check #impl_generics (_x: #name #ty_generics) #where_clause {
+                #check_each_field_dupe
+            }
+        };
+    })
 }
 
-fn check_each_field_dupe<'a>(tys: impl IntoIterator<Item = &'a Type>) -> TokenStream {
-    let tys = tys.into_iter();
+/// Assert each field is `Dupe`, spanned at the field so errors point at the offending field.
+///
+/// The error names the field, and with `skip_check_hint` suggests `#[dupe(skip_check)]`.
+/// The message comes from a trait local to the check, as the diagnostic of the outermost
+/// unsatisfied bound is the one reported.
+fn check_each_field_dupe<'a>(
+    fields: impl IntoIterator<Item = &'a Field>,
+    skip_check_hint: bool,
+) -> TokenStream {
+    let checks = fields.into_iter().map(|f| {
+        let ty = &f.ty;
+        let (message, field) = match &f.ident {
+            Some(name) => (
+                format!("field `{name}` of type `{{Self}}` is not cheap to clone"),
+                format!("the field `{name}`"),
+            ),
+            None => (
+                "field of type `{Self}` is not cheap to clone".to_owned(),
+                "the field".to_owned(),
+            ),
+        };
+        let note = if skip_check_hint {
+            format!(
+                "consider wrapping it in `Arc`, or if it is cheap to clone but can't implement `Dupe`, mark {field} with `#[dupe(skip_check)]`"
+            )
+        } else {
+            "consider wrapping it in `Arc`".to_owned()
+        };
+        quote_spanned! {f.span()=>
+            {
+                #[diagnostic::on_unimplemented(
+                    message = #message,
+                    label = "`{Self}` does not implement `Dupe`",
+                    note = #note
+                )]
+                trait DupeField {}
+                impl<T: dupe::Dupe + ?::core::marker::Sized> DupeField for T {}
+                const fn assert_dupe_field<T: DupeField + ?::core::marker::Sized>() {}
+                assert_dupe_field::<#ty>();
+            }
+        }
+    });
     quote! {
-        #(
-            dupe::__macro_refs::assert_dupe::<#tys>();
-        )*
+        #(#checks)*
     }
 }
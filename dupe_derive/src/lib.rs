@@ -8,24 +8,47 @@
  * above-listed licenses.
  */
 
+mod attrs;
 mod clone;
 mod copy;
 mod dupe;
 mod util;
 
 /// Derive the `Dupe` trait.
-#[proc_macro_derive(Dupe)]
+///
+/// Every field type which mentions a type parameter is required to implement `Dupe`, so
+/// `struct Handle<T>(Arc<T>)` is `Dupe` for any `T`, while `struct Wrap<T>(T)` requires `T: Dupe`.
+/// The remaining fields are checked to be `Dupe`. The behaviour can be adjusted with attributes:
+///
+/// * `#[dupe(bound = "T: Dupe, U: Send")]` on the type replaces the generated bounds.
+/// * `#[dupe(skip_check)]` on a field skips the `Dupe` check for that field, for types which are
+///   cheap to clone but don't implement `Dupe`.
+#[proc_macro_derive(Dupe, attributes(dupe))]
 pub fn derive_dupe(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     dupe::derive_dupe(input)
 }
 
 /// Derive the `Dupe` trait, but without requiring all type arguments to implement `Dupe`.
-#[proc_macro_derive(Dupe_)]
+///
+/// Accepts the same attributes as `Dupe`.
+#[proc_macro_derive(Dupe_, attributes(dupe))]
 pub fn derive_dupe_(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     dupe::derive_dupe_(input)
 }
 
+/// Derive both the [`Clone` trait](Clone) and the `Dupe` trait, where `clone` calls `dupe`
+/// on every field.
+///
+/// Unlike `#[derive(Clone, Dupe)]`, the generated `clone` can't perform a deep clone, so adding
+/// a field which isn't `Dupe` is a compile error. Every field type which mentions a type parameter
+/// is required to implement `Dupe`. Accepts `#[dupe(bound = "...")]` on the type.
+#[proc_macro_derive(DupeClone, attributes(dupe))]
+pub fn derive_dupe_clone(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    dupe::derive_dupe_clone(input)
+}
+
 /// Derive the [`Clone` trait](Clone), but without requiring all type arguments to implement [`Clone`].
+/// Only the field types which mention a type parameter are required to implement [`Clone`].
 #[proc_macro_derive(Clone_)]
 pub fn derive_clone_(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     clone::derive_clone_(input)
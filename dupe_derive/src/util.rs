@@ -15,23 +15,88 @@ use quote::quote;
 use syn::Data;
 use syn::DataEnum;
 use syn::DataStruct;
+use syn::Field;
 use syn::Fields;
-use syn::GenericParam;
 use syn::Generics;
 use syn::Ident;
 use syn::Index;
+use syn::Path;
 use syn::Type;
 use syn::TypeParamBound;
 use syn::Variant;
+use syn::WherePredicate;
+use syn::parse_quote;
 use syn::spanned::Spanned;
+use syn::visit;
+use syn::visit::Visit;
 
-// Add a bound to every type parameter.
-pub(crate) fn add_trait_bounds(mut generics: Generics, bound: &TypeParamBound) -> Generics {
-    for param in &mut generics.params {
-        if let GenericParam::Type(ref mut type_param) = *param {
-            type_param.bounds.push(bound.clone());
+/// Collects the type parameters mentioned by a type.
+struct MentionedTypeParams<'a> {
+    params: &'a [&'a Ident],
+    self_name: &'a Ident,
+    mentioned: Vec<&'a Ident>,
+    mentions_self: bool,
+}
+
+impl<'ast> Visit<'ast> for MentionedTypeParams<'_> {
+    fn visit_path(&mut self, path: &'ast Path) {
+        if path.leading_colon.is_none()
+            && let Some(first) = path.segments.first()
+        {
+            if let Some(param) = self.params.iter().find(|p| **p == &first.ident)
+                && !self.mentioned.contains(param)
+            {
+                self.mentioned.push(param);
+            }
+            if path.segments.len() == 1 && &first.ident == self.self_name {
+                self.mentions_self = true;
+            }
+        }
+        visit::visit_path(self, path);
+    }
+}
+
+/// Add a bound to each field type which mentions a type parameter, e.g. `Arc<T>: Dupe`.
+/// Fields which don't mention any type parameter need no bound, so `Arc<T>` or
+/// `PhantomData<T>` fields don't require anything of `T`.
+///
+/// Recursive fields (those which mention the type being derived) would send the trait solver
+/// into a cycle, so for those we bound the type parameters they mention instead.
+pub(crate) fn add_field_bounds<'a>(
+    mut generics: Generics,
+    self_name: &Ident,
+    field_tys: impl IntoIterator<Item = &'a Type>,
+    bound: &TypeParamBound,
+) -> Generics {
+    let params: Vec<Ident> = generics.type_params().map(|p| p.ident.clone()).collect();
+    if params.is_empty() {
+        return generics;
+    }
+    let params: Vec<&Ident> = params.iter().collect();
+
+    let mut predicates: Vec<WherePredicate> = Vec::new();
+    let mut push = |predicate: WherePredicate| {
+        if !predicates.contains(&predicate) {
+            predicates.push(predicate);
+        }
+    };
+    for ty in field_tys {
+        let mut visitor = MentionedTypeParams {
+            params: &params,
+            self_name,
+            mentioned: Vec::new(),
+            mentions_self: false,
+        };
+        visitor.visit_type(ty);
+        if visitor.mentions_self {
+            for param in visitor.mentioned {
+                push(parse_quote!(#param: #bound));
+            }
+        } else if !visitor.mentioned.is_empty() {
+            push(parse_quote!(#ty: #bound));
         }
     }
+    generics.make_where_clause().predicates.extend(predicates);
     generics
 }
 
@@ -137,12 +202,12 @@ pub(crate) fn duplicate_impl(data: &Data, duplicate: &TokenStream) -> TokenStrea
     }
 }
 
-pub(crate) fn extract_all_field_tys<'a>(
+pub(crate) fn extract_all_fields<'a>(
     data: &'a Data,
-) -> Result<Box<dyn Iterator<Item = &'a Type> + 'a>, syn::Error> {
+) -> Result<Box<dyn Iterator<Item = &'a Field> + 'a>, syn::Error> {
     match data {
-        Data::Struct(data) => Ok(extract_all_field_tys_struct(data)),
-        Data::Enum(data) => Ok(extract_all_field_tys_enum(data)),
+        Data::Struct(data) => Ok(extract_all_fields_struct(data)),
+        Data::Enum(data) => Ok(extract_all_fields_enum(data)),
         Data::Union(x) => Err(syn::Error::new_spanned(
             x.union_token,
             "Can't derive duplication for unions",
@@ -150,24 +215,22 @@ pub(crate) fn extract_all_field_tys<'a>(
     }
 }
 
-fn extract_all_field_tys_struct<'a>(
-    data: &'a DataStruct,
-) -> Box<dyn Iterator<Item = &'a Type> + 'a> {
+fn extract_all_fields_struct<'a>(data: &'a DataStruct) -> Box<dyn Iterator<Item = &'a Field> + 'a> {
     match data.fields {
-        Fields::Named(ref fields) => Box::new(fields.named.iter().map(|f| &f.ty)),
-        Fields::Unnamed(ref fields) => Box::new(fields.unnamed.iter().map(|f| &f.ty)),
+        Fields::Named(ref fields) => Box::new(fields.named.iter()),
+        Fields::Unnamed(ref fields) => Box::new(fields.unnamed.iter()),
         Fields::Unit => Box::new(iter::empty()),
     }
 }
 
-fn extract_all_field_tys_variant<'a>(data: &'a Variant) -> Box<dyn Iterator<Item = &'a Type> + 'a> {
+fn extract_all_fields_variant<'a>(data: &'a Variant) -> Box<dyn Iterator<Item = &'a Field> + 'a> {
     match data.fields {
-        Fields::Named(ref fields) => Box::new(fields.named.iter().map(|f| &f.ty)),
-        Fields::Unnamed(ref fields) => Box::new(fields.unnamed.iter().map(|f| &f.ty)),
+        Fields::Named(ref fields) => Box::new(fields.named.iter()),
+        Fields::Unnamed(ref fields) => Box::new(fields.unnamed.iter()),
         Fields::Unit => Box::new(iter::empty()),
     }
 }
 
-fn extract_all_field_tys_enum<'a>(data: &'a DataEnum) -> Box<dyn Iterator<Item = &'a Type> + 'a> {
-    Box::new(data.variants.iter().flat_map(extract_all_field_tys_variant))
+fn extract_all_fields_enum<'a>(data: &'a DataEnum) -> Box<dyn Iterator<Item = &'a Field> + 'a> {
+    Box::new(data.variants.iter().flat_map(extract_all_fields_variant))
 }
@@ -0,0 +1,484 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+//! A reference counted immutable string, which is a single pointer wide.
+//!
+//! The reference count and length are stored in a header in front of the string data,
+//! so [`ArcStr`] is half the size of an `Arc<str>`. Strings created from literals with
+//! [`static_arc_str!`](crate::static_arc_str) point at static memory and are never counted.
+//!
+//! A thin pointer has no room for a range, so substrings are a separate type, [`Substr`],
+//! which holds an [`ArcStr`] and the range within it. Both implement the same traits as
+//! [`str`], by forwarding to it.
+//!
+//! ```
+//! use std::collections::HashMap;
+//!
+//! use gazebo::arc_str::ArcStr;
+//! use gazebo::static_arc_str;
+//!
+//! let mut map = HashMap::new();
+//! map.insert(ArcStr::from("hello"), 1);
+//! map.insert(static_arc_str!("world"), 2);
+//! assert_eq!(map.get("hello"), Some(&1));
+//! assert_eq!(map.get("world"), Some(&2));
+//! ```
+
+// Layout: `ArcStr` points at a `Header`, which is immediately followed by `len` bytes of UTF-8.
+// A `count` of zero means the string is static: counts of heap strings are at least one while
+// any reference exists, and the count of a static string is never modified.
+
+use std::alloc;
+use std::alloc::Layout;
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::mem;
+use std::ops::Bound;
+use std::ops::Deref;
+use std::ops::RangeBounds;
+use std::ptr;
+use std::ptr::NonNull;
+use std::slice;
+use std::str;
+use std::sync::atomic;
+use std::sync::atomic::AtomicUsize;
+
+use dupe::Dupe;
+
+#[repr(C)]
+struct Header {
+    count: AtomicUsize,
+    len: usize,
+}
+
+const DATA_OFFSET: usize = mem::size_of::<Header>();
+
+fn heap_layout(len: usize) -> Layout {
+    Layout::new::<Header>()
+        .extend(Layout::array::<u8>(len).expect("string too large"))
+        .expect("string too large")
+        .0
+        .pad_to_align()
+}
+
+/// The static storage of an [`ArcStr`], usually created with
+/// [`static_arc_str!`](crate::static_arc_str).
+#[repr(C)]
+pub struct StaticArcStr<const N: usize> {
+    header: Header,
+    data: [u8; N],
+}
+
+impl<const N: usize> StaticArcStr<N> {
+    /// Create the storage for a static string. Panics if `s` is not `N` bytes long.
+    pub const fn new(s: &str) -> Self {
+        assert!(s.len() == N, "`StaticArcStr` length mismatch");
+        let bytes = s.as_bytes();
+        let mut data = [0; N];
+        let mut i = 0;
+        while i < N {
+            data[i] = bytes[i];
+            i += 1;
+        }
+        StaticArcStr {
+            header: Header {
+                count: AtomicUsize::new(0),
+                len: N,
+            },
+            data,
+        }
+    }
+}
+
+/// Create an [`ArcStr`] from a string literal, which points at static memory and is never
+/// reference counted.
+///
+/// ```
+/// use gazebo::static_arc_str;
+///
+/// let s = static_arc_str!("hello");
+/// assert_eq!(&*s, "hello");
+/// ```
+#[macro_export]
+macro_rules! static_arc_str {
+    ($s:expr) => {{
+        const S: &str = $s;
+        static STORAGE: $crate::arc_str::StaticArcStr<{ S.len() }> =
+            $crate::arc_str::StaticArcStr::new(S);
+        $crate::arc_str::ArcStr::from_static_storage(&STORAGE)
+    }};
+}
+
+/// A reference counted immutable string, which is a single pointer wide.
+pub struct ArcStr {
+    ptr: NonNull<Header>,
+}
+
+// `ArcStr` is an immutable string with an atomic reference count, like `Arc<str>`.
+unsafe impl Send for ArcStr {}
+unsafe impl Sync for ArcStr {}
+
+static EMPTY: StaticArcStr<0> = StaticArcStr::new("");
+
+impl ArcStr {
+    /// Wrap static storage, without any allocation or reference counting. A `&'static str`
+    /// has no header in front of it, so it must be copied into a [`StaticArcStr`] first,
+    /// which [`static_arc_str!`](crate::static_arc_str) does at compile time.
+    pub const fn from_static_storage<const N: usize>(s: &'static StaticArcStr<N>) -> ArcStr {
+        // The pointer is derived from the whole `StaticArcStr`, so it can access the data.
+        ArcStr {
+            ptr: unsafe { NonNull::new_unchecked(s as *const StaticArcStr<N> as *mut Header) },
+        }
+    }
+
+    /// The empty string.
+    pub const fn new() -> ArcStr {
+        ArcStr::from_static_storage(&EMPTY)
+    }
+
+    fn count(&self) -> &AtomicUsize {
+        unsafe { &(*self.ptr.as_ptr()).count }
+    }
+
+    /// Is this string stored in static memory.
+    pub fn is_static(&self) -> bool {
+        self.count().load(atomic::Ordering::Relaxed) == 0
+    }
+
+    /// Get the string.
+    pub fn as_str(&self) -> &str {
+        unsafe {
+            let len = (*self.ptr.as_ptr()).len;
+            let data = self.ptr.as_ptr().cast::<u8>().add(DATA_OFFSET);
+            str::from_utf8_unchecked(slice::from_raw_parts(data, len))
+        }
+    }
+
+    /// Do the two strings share the same storage.
+    pub fn ptr_eq(this: &ArcStr, other: &ArcStr) -> bool {
+        this.ptr == other.ptr
+    }
+
+    /// A substring which shares the storage of this string, without copying.
+    ///
+    /// Panics if the range is out of bounds or not on a `char` boundary.
+    ///
+    /// ```
+    /// use gazebo::arc_str::ArcStr;
+    ///
+    /// let s = ArcStr::from("hello world");
+    /// let sub = s.substr(6..);
+    /// assert_eq!(&*sub, "world");
+    /// assert!(ArcStr::ptr_eq(sub.parent(), &s));
+    /// ```
+    pub fn substr(&self, range: impl RangeBounds<usize>) -> Substr {
+        Substr::from(self.dupe()).substr(range)
+    }
+}
+
+impl From<&str> for ArcStr {
+    fn from(s: &str) -> ArcStr {
+        if s.is_empty() {
+            return ArcStr::new();
+        }
+        let layout = heap_layout(s.len());
+        unsafe {
+            let ptr = alloc::alloc(layout).cast::<Header>();
+            let Some(ptr) = NonNull::new(ptr) else {
+                alloc::handle_alloc_error(layout)
+            };
+            ptr.as_ptr().write(Header {
+                count: AtomicUsize::new(1),
+                len: s.len(),
+            });
+            ptr::copy_nonoverlapping(
+                s.as_ptr(),
+                ptr.as_ptr().cast::<u8>().add(DATA_OFFSET),
+                s.len(),
+            );
+            ArcStr { ptr }
+        }
+    }
+}
+
+impl From<String> for ArcStr {
+    fn from(s: String) -> ArcStr {
+        ArcStr::from(s.as_str())
+    }
+}
+
+impl From<&String> for ArcStr {
+    fn from(s: &String) -> ArcStr {
+        ArcStr::from(s.as_str())
+    }
+}
+
+impl Clone for ArcStr {
+    fn clone(&self) -> Self {
+        if !self.is_static() {
+            // Same as `Arc`, a `Relaxed` increment is enough as we already hold a reference.
+            let old = self.count().fetch_add(1, atomic::Ordering::Relaxed);
+            if old > isize::MAX as usize {
+                std::process::abort();
+            }
+        }
+        ArcStr { ptr: self.ptr }
+    }
+}
+
+impl Dupe for ArcStr {}
+
+impl Drop for ArcStr {
+    fn drop(&mut self) {
+        if self.is_static() || self.count().fetch_sub(1, atomic::Ordering::Release) != 1 {
+            return;
+        }
+        atomic::fence(atomic::Ordering::Acquire);
+        unsafe {
+            let layout = heap_layout((*self.ptr.as_ptr()).len);
+            alloc::dealloc(self.ptr.as_ptr().cast(), layout);
+        }
+    }
+}
+
+impl Default for ArcStr {
+    fn default() -> Self {
+        ArcStr::new()
+    }
+}
+
+/// A substring of an [`ArcStr`], sharing its storage. Created by [`ArcStr::substr`].
+///
+/// This is three words, rather than storing the range in [`ArcStr`] itself, so that whole
+/// strings, by far the common case, stay a single pointer wide.
+#[derive(Clone, Dupe)]
+pub struct Substr {
+    parent: ArcStr,
+    start: usize,
+    end: usize,
+}
+
+impl Substr {
+    /// The string this is a substring of.
+    pub fn parent(&self) -> &ArcStr {
+        &self.parent
+    }
+
+    /// Get the string.
+    pub fn as_str(&self) -> &str {
+        // The range was checked when the substring was created.
+        unsafe { self.parent.as_str().get_unchecked(self.start..self.end) }
+    }
+
+    /// A substring of this substring, sharing the same storage.
+    ///
+    /// Panics if the range is out of bounds or not on a `char` boundary.
+    pub fn substr(&self, range: impl RangeBounds<usize>) -> Substr {
+        let bounds: (Bound<usize>, Bound<usize>) =
+            (range.start_bound().cloned(), range.end_bound().cloned());
+        let sub = &self.as_str()[bounds];
+        let start = sub.as_ptr() as usize - self.parent.as_ptr() as usize;
+        Substr {
+            parent: self.parent.dupe(),
+            start,
+            end: start + sub.len(),
+        }
+    }
+}
+
+impl From<ArcStr> for Substr {
+    fn from(parent: ArcStr) -> Substr {
+        Substr {
+            start: 0,
+            end: parent.len(),
+            parent,
+        }
+    }
+}
+
+// Both string types forward everything to `as_str`. In particular comparisons and hashing go
+// through `str`, as required by `Borrow<str>`.
+macro_rules! impl_str_traits {
+    ($t:ty) => {
+        impl Deref for $t {
+            type Target = str;
+
+            fn deref(&self) -> &str {
+                self.as_str()
+            }
+        }
+
+        impl AsRef<str> for $t {
+            fn as_ref(&self) -> &str {
+                self.as_str()
+            }
+        }
+
+        impl Borrow<str> for $t {
+            fn borrow(&self) -> &str {
+                self.as_str()
+            }
+        }
+
+        impl Display for $t {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                Display::fmt(self.as_str(), f)
+            }
+        }
+
+        impl Debug for $t {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                Debug::fmt(self.as_str(), f)
+            }
+        }
+
+        impl PartialEq for $t {
+            fn eq(&self, other: &Self) -> bool {
+                self.as_str() == other.as_str()
+            }
+        }
+
+        impl Eq for $t {}
+
+        impl PartialEq<str> for $t {
+            fn eq(&self, other: &str) -> bool {
+                self.as_str() == other
+            }
+        }
+
+        impl PartialEq<&str> for $t {
+            fn eq(&self, other: &&str) -> bool {
+                self.as_str() == *other
+            }
+        }
+
+        impl PartialOrd for $t {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for $t {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.as_str().cmp(other.as_str())
+            }
+        }
+
+        impl Hash for $t {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.as_str().hash(state)
+            }
+        }
+
+        #[cfg(feature = "strong_hash")]
+        impl strong_hash::StrongHash for $t {
+            fn strong_hash<H: Hasher>(&self, state: &mut H) {
+                self.as_str().strong_hash(state)
+            }
+        }
+    };
+}
+
+impl_str_traits!(ArcStr);
+impl_str_traits!(Substr);
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn test_size() {
+        assert_eq!(mem::size_of::<ArcStr>(), mem::size_of::<usize>());
+        assert_eq!(mem::size_of::<Option<ArcStr>>(), mem::size_of::<usize>());
+    }
+
+    #[test]
+    fn test_count() {
+        let s = ArcStr::from("test".to_owned());
+        assert_eq!(s.count().load(atomic::Ordering::Relaxed), 1);
+        let s2 = s.dupe();
+        let sub = s.substr(1..3);
+        assert_eq!(s.count().load(atomic::Ordering::Relaxed), 3);
+        drop(s2);
+        drop(sub);
+        assert_eq!(s.count().load(atomic::Ordering::Relaxed), 1);
+        assert!(!s.is_static());
+    }
+
+    #[test]
+    fn test_static() {
+        let s = static_arc_str!("static");
+        assert!(s.is_static());
+        let s2 = s.dupe();
+        assert!(ArcStr::ptr_eq(&s, &s2));
+        assert_eq!(s, "static");
+        assert!(ArcStr::new().is_static());
+        assert!(ArcStr::from("").is_static());
+        assert_eq!(ArcStr::default(), "");
+    }
+
+    #[test]
+    fn test_substr() {
+        let s = ArcStr::from("hello, world");
+        let sub = s.substr(..5);
+        assert_eq!(sub, "hello");
+        assert_eq!(sub.substr(1..=2), "el");
+        assert_eq!(s.substr(7..).substr(..), "world");
+        assert_eq!(s.substr(5..5), "");
+        // Multi-byte characters are respected.
+        let s = ArcStr::from("a\u{e9}b");
+        assert_eq!(s.substr(1..3), "\u{e9}");
+        assert!(std::panic::catch_unwind(|| s.substr(2..)).is_err());
+    }
+
+    #[test]
+    fn test_borrow() {
+        let set: HashSet<ArcStr> = ["a", "b"].into_iter().map(ArcStr::from).collect();
+        assert!(set.contains("a"));
+        let set: HashSet<Substr> = [ArcStr::from("xa").substr(1..)].into_iter().collect();
+        assert!(set.contains("a"));
+    }
+
+    #[test]
+    fn test_display() {
+        let s = ArcStr::from("x");
+        assert_eq!(format!("[{s:>3}]"), "[  x]");
+        assert_eq!(format!("{:<3}|", s.substr(..)), "x  |");
+        assert_eq!(format!("{:?}", s.substr(..)), "\"x\"");
+    }
+
+    #[test]
+    fn test_threads() {
+        let s = ArcStr::from("shared");
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let s = s.dupe();
+                thread::spawn(move || {
+                    for _ in 0..1000 {
+                        drop(s.dupe());
+                    }
+                    s.len()
+                })
+            })
+            .collect();
+        for h in handles {
+            assert_eq!(h.join().unwrap(), 6);
+        }
+        assert_eq!(s.count().load(atomic::Ordering::Relaxed), 1);
+    }
+}
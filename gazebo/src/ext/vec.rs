@@ -299,6 +299,28 @@ pub trait VecExt {
     fn into_try_map<B, E, F>(self, f: F) -> Result<Vec<B>, E>
     where
         F: FnMut(Self::Item) -> Result<B, E>;
+
+    /// Create a vector by duping each element of `iter`, analogous to
+    /// `iter.into_iter().cloned().collect()` but requiring `Dupe`.
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use std::rc::Rc;
+    ///
+    /// use gazebo::prelude::*;
+    ///
+    /// let map = BTreeMap::from([(1, Rc::new("one")), (2, Rc::new("two"))]);
+    /// let values = Vec::dupe_from_iter(map.values());
+    /// assert_eq!(values, vec![Rc::new("one"), Rc::new("two")]);
+    /// ```
+    fn dupe_from_iter<'a, I>(iter: I) -> Self
+    where
+        Self: FromIterator<Self::Item>,
+        Self::Item: Dupe + 'a,
+        I: IntoIterator<Item = &'a Self::Item>,
+    {
+        iter.into_iter().map(Dupe::dupe).collect()
+    }
 }
 
 impl<T> VecExt for Vec<T> {
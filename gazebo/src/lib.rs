@@ -12,12 +12,14 @@
 
 //! A collection of well-tested primitives that have been useful. Most modules stand alone.
 
+pub mod arc_str;
 pub mod cast;
 pub mod cell;
 pub mod cmp;
 pub(crate) mod ext;
 pub mod file;
 pub mod hash;
+pub mod persistent;
 pub mod phantom;
 pub mod prelude;
 pub mod types;
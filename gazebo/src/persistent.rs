@@ -0,0 +1,31 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+//! Persistent collections, where `clone` is O(1) and the clones share structure.
+//!
+//! Modifying a collection only copies the nodes on the path to the modified element, so
+//! values which are "mostly immutable, occasionally modified" can be passed around with
+//! [`Dupe`](dupe::Dupe) rather than as `Arc<Vec<T>>` which must be copied in full on every change.
+//!
+//! ```
+//! use gazebo::persistent::PVec;
+//!
+//! let mut xs: PVec<_> = (0..100).collect();
+//! let ys = xs.clone();
+//! xs.push(100);
+//! assert_eq!(xs.len(), 101);
+//! assert_eq!(ys.len(), 100);
+//! ```
+
+mod map;
+mod vec;
+
+pub use map::PMap;
+pub use vec::PVec;
@@ -0,0 +1,842 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+// A hash array mapped trie (HAMT). Each node consumes `BITS` bits of the key hash, and stores
+// its children densely, with a bitmap recording which of the `WIDTH` slots are occupied.
+// Keys whose full hashes are equal are stored together in a collision bucket.
+//
+// Invariant: every node other than the root has at least two children, or a single child
+// which is itself a node. Removal pulls lone entries back up to maintain it.
+
+use std::borrow::Borrow;
+use std::fmt;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::iter::FusedIterator;
+use std::mem;
+use std::ops::Index;
+use std::slice;
+use std::sync::Arc;
+
+use dupe::Dupe;
+
+const BITS: u32 = 5;
+const MASK: u64 = (1 << BITS) - 1;
+
+#[derive(Clone)]
+enum Child<K, V> {
+    Entry(u64, K, V),
+    // At least two entries, all with the given hash.
+    Collision(u64, Arc<Vec<(K, V)>>),
+    Node(Arc<Node<K, V>>),
+}
+
+#[derive(Clone)]
+struct Node<K, V> {
+    bitmap: u32,
+    children: Vec<Child<K, V>>,
+}
+
+fn hash_key<Q: Hash + ?Sized>(key: &Q) -> u64 {
+    let mut hasher = KeyHasher::default();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The hasher for keys. This is FxHash, written out here rather than taken from `std`
+/// (whose algorithm may change between releases), so the layout (and iteration order)
+/// of a map depends only on its contents and their `Hash` implementations.
+#[derive(Default)]
+struct KeyHasher(u64);
+
+impl KeyHasher {
+    const SEED: u64 = 0x517c_c1b7_2722_0a95;
+
+    fn add(&mut self, word: u64) {
+        self.0 = (self.0.rotate_left(5) ^ word).wrapping_mul(Self::SEED);
+    }
+}
+
+impl Hasher for KeyHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        let mut chunks = bytes.chunks_exact(8);
+        for chunk in &mut chunks {
+            self.add(u64::from_le_bytes(chunk.try_into().unwrap()));
+        }
+        let rest = chunks.remainder();
+        if !rest.is_empty() {
+            let mut word = [0; 8];
+            word[..rest.len()].copy_from_slice(rest);
+            self.add(u64::from_le_bytes(word));
+        }
+    }
+
+    // Integers are added as words, rather than as their native-endian bytes, so hashes
+    // agree across platforms.
+    fn write_u8(&mut self, i: u8) {
+        self.add(i as u64);
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.add(i as u64);
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.add(i as u64);
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.add(i);
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.add(i as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        // The multiply leaves the best mixed bits at the top, but the trie consumes the
+        // hash from the bottom.
+        self.0.rotate_left(26)
+    }
+}
+
+fn bit(hash: u64, shift: u32) -> u32 {
+    1 << ((hash >> shift) & MASK)
+}
+
+impl<K, V> Node<K, V> {
+    fn empty() -> Self {
+        Node {
+            bitmap: 0,
+            children: Vec::new(),
+        }
+    }
+
+    /// The position in `children` of the child with the given bit, if it were present.
+    fn position(&self, bit: u32) -> usize {
+        (self.bitmap & (bit - 1)).count_ones() as usize
+    }
+
+    fn get<Q>(&self, hash: u64, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let mut node = self;
+        let mut shift = 0;
+        loop {
+            let bit = bit(hash, shift);
+            if node.bitmap & bit == 0 {
+                return None;
+            }
+            match &node.children[node.position(bit)] {
+                Child::Entry(h, k, v) => {
+                    return (*h == hash && k.borrow() == key).then_some((k, v));
+                }
+                Child::Collision(h, entries) => {
+                    if *h != hash {
+                        return None;
+                    }
+                    return entries
+                        .iter()
+                        .find(|(k, _)| k.borrow() == key)
+                        .map(|(k, v)| (k, v));
+                }
+                Child::Node(n) => {
+                    node = n;
+                    shift += BITS;
+                }
+            }
+        }
+    }
+
+    fn map<B>(&self, f: &mut impl FnMut(&K, &V) -> B) -> Node<K, B>
+    where
+        K: Clone,
+    {
+        Node {
+            bitmap: self.bitmap,
+            children: self
+                .children
+                .iter()
+                .map(|c| match c {
+                    Child::Entry(h, k, v) => Child::Entry(*h, k.clone(), f(k, v)),
+                    Child::Collision(h, entries) => Child::Collision(
+                        *h,
+                        Arc::new(entries.iter().map(|(k, v)| (k.clone(), f(k, v))).collect()),
+                    ),
+                    Child::Node(n) => Child::Node(Arc::new(n.map(f))),
+                })
+                .collect(),
+        }
+    }
+
+    fn try_map<B, E>(&self, f: &mut impl FnMut(&K, &V) -> Result<B, E>) -> Result<Node<K, B>, E>
+    where
+        K: Clone,
+    {
+        Ok(Node {
+            bitmap: self.bitmap,
+            children: self
+                .children
+                .iter()
+                .map(|c| {
+                    Ok(match c {
+                        Child::Entry(h, k, v) => Child::Entry(*h, k.clone(), f(k, v)?),
+                        Child::Collision(h, entries) => Child::Collision(
+                            *h,
+                            Arc::new(
+                                entries
+                                    .iter()
+                                    .map(|(k, v)| Ok((k.clone(), f(k, v)?)))
+                                    .collect::<Result<_, E>>()?,
+                            ),
+                        ),
+                        Child::Node(n) => Child::Node(Arc::new(n.try_map(f)?)),
+                    })
+                })
+                .collect::<Result<_, E>>()?,
+        })
+    }
+}
+
+impl<K: Eq + Clone, V: Clone> Node<K, V> {
+    /// A node containing two children with different hashes.
+    fn pair(shift: u32, a: Child<K, V>, a_hash: u64, b: Child<K, V>, b_hash: u64) -> Self {
+        let a_bit = bit(a_hash, shift);
+        let b_bit = bit(b_hash, shift);
+        let children = if a_bit == b_bit {
+            // The hashes differ somewhere, so this terminates before running out of bits.
+            vec![Child::Node(Arc::new(Node::pair(
+                shift + BITS,
+                a,
+                a_hash,
+                b,
+                b_hash,
+            )))]
+        } else if a_bit < b_bit {
+            vec![a, b]
+        } else {
+            vec![b, a]
+        };
+        Node {
+            bitmap: a_bit | b_bit,
+            children,
+        }
+    }
+
+    fn insert(&mut self, shift: u32, hash: u64, key: K, value: V) -> Option<V> {
+        let bit = bit(hash, shift);
+        let pos = self.position(bit);
+        if self.bitmap & bit == 0 {
+            self.bitmap |= bit;
+            self.children.insert(pos, Child::Entry(hash, key, value));
+            return None;
+        }
+        match &mut self.children[pos] {
+            Child::Node(n) => return Arc::make_mut(n).insert(shift + BITS, hash, key, value),
+            Child::Entry(h, k, v) if *h == hash && *k == key => {
+                return Some(mem::replace(v, value));
+            }
+            Child::Collision(h, entries) if *h == hash => {
+                let entries = Arc::make_mut(entries);
+                return match entries.iter_mut().find(|(k, _)| *k == key) {
+                    Some((_, v)) => Some(mem::replace(v, value)),
+                    None => {
+                        entries.push((key, value));
+                        None
+                    }
+                };
+            }
+            _ => {}
+        }
+        // The slot holds a different key, so split it.
+        let new = match self.children.remove(pos) {
+            Child::Entry(h, k, v) if h == hash => {
+                Child::Collision(hash, Arc::new(vec![(k, v), (key, value)]))
+            }
+            old @ (Child::Entry(h, ..) | Child::Collision(h, _)) => Child::Node(Arc::new(
+                Node::pair(shift + BITS, old, h, Child::Entry(hash, key, value), hash),
+            )),
+            Child::Node(_) => unreachable!("nodes are handled above"),
+        };
+        self.children.insert(pos, new);
+        None
+    }
+
+    /// Remove `key`, which must be present, as otherwise shared nodes are copied needlessly.
+    fn remove<Q>(&mut self, shift: u32, hash: u64, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let bit = bit(hash, shift);
+        if self.bitmap & bit == 0 {
+            return None;
+        }
+        let pos = self.position(bit);
+        match &mut self.children[pos] {
+            Child::Node(n) => {
+                let n = Arc::make_mut(n);
+                let res = n.remove(shift + BITS, hash, key);
+                if n.children.len() == 1 && !matches!(n.children[0], Child::Node(_)) {
+                    let child = n.children.pop().unwrap();
+                    self.children[pos] = child;
+                }
+                return res;
+            }
+            Child::Collision(h, entries) if *h == hash => {
+                let entries = Arc::make_mut(entries);
+                let i = entries.iter().position(|(k, _)| k.borrow() == key)?;
+                let (_, v) = entries.remove(i);
+                if entries.len() == 1 {
+                    let (k, v) = entries.pop().unwrap();
+                    self.children[pos] = Child::Entry(hash, k, v);
+                }
+                return Some(v);
+            }
+            Child::Entry(h, k, _) if *h == hash && (*k).borrow() == key => {}
+            _ => return None,
+        }
+        self.bitmap &= !bit;
+        match self.children.remove(pos) {
+            Child::Entry(_, _, v) => Some(v),
+            _ => unreachable!("only entries are removed directly"),
+        }
+    }
+
+    fn get_mut<Q>(&mut self, hash: u64, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let mut node = self;
+        let mut shift = 0;
+        loop {
+            let bit = bit(hash, shift);
+            if node.bitmap & bit == 0 {
+                return None;
+            }
+            let pos = node.position(bit);
+            match &mut node.children[pos] {
+                Child::Entry(h, k, v) => {
+                    return (*h == hash && (*k).borrow() == key).then_some(v);
+                }
+                Child::Collision(h, entries) => {
+                    if *h != hash {
+                        return None;
+                    }
+                    return Arc::make_mut(entries)
+                        .iter_mut()
+                        .find(|(k, _)| (*k).borrow() == key)
+                        .map(|(_, v)| v);
+                }
+                Child::Node(n) => {
+                    node = Arc::make_mut(n);
+                    shift += BITS;
+                }
+            }
+        }
+    }
+}
+
+/// A persistent hash map, with O(1) `clone` and O(log n) `insert`, `remove`, `get` and
+/// `get_mut`.
+///
+/// Clones share structure, and modifying a shared map only copies the nodes on the path to
+/// the modified entry. Modifying operations therefore require `K: Clone` and `V: Clone`, as
+/// entries in those nodes are cloned, so keys and values should usually be [`Dupe`].
+///
+/// Entries are iterated in an order determined by the hashes of their keys, which doesn't depend
+/// on the order of insertion (apart from keys whose hashes are equal).
+///
+/// ```
+/// use gazebo::persistent::PMap;
+///
+/// let mut xs = PMap::new();
+/// xs.insert("a", 1);
+/// let mut ys = xs.clone();
+/// ys.insert("b", 2);
+/// assert_eq!(xs.get("b"), None);
+/// assert_eq!(ys.get("b"), Some(&2));
+/// ```
+pub struct PMap<K, V> {
+    len: usize,
+    root: Arc<Node<K, V>>,
+}
+
+impl<K, V> PMap<K, V> {
+    /// Create a new empty map.
+    pub fn new() -> Self {
+        PMap {
+            len: 0,
+            root: Arc::new(Node::empty()),
+        }
+    }
+
+    /// The number of entries in the map.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Is the map empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterate over the entries of the map.
+    pub fn iter(&self) -> PMapIter<'_, K, V> {
+        PMapIter {
+            stack: vec![self.root.children.iter()],
+            collision: [].iter(),
+            len: self.len,
+        }
+    }
+
+    /// Iterate over the keys of the map.
+    pub fn keys(&self) -> impl ExactSizeIterator<Item = &K> {
+        self.iter().map(|(k, _)| k)
+    }
+
+    /// Iterate over the values of the map.
+    pub fn values(&self) -> impl ExactSizeIterator<Item = &V> {
+        self.iter().map(|(_, v)| v)
+    }
+
+    /// Create a new map by applying `f` to each entry, preserving the structure
+    /// of `self` rather than rehashing every key.
+    ///
+    /// ```
+    /// use gazebo::persistent::PMap;
+    ///
+    /// let xs: PMap<_, _> = [("a", 1), ("b", 2)].into_iter().collect();
+    /// let ys = xs.map(|k, v| format!("{k}{v}"));
+    /// assert_eq!(ys.get("b").map(|v| v.as_str()), Some("b2"));
+    /// ```
+    pub fn map<B, F>(&self, mut f: F) -> PMap<K, B>
+    where
+        K: Clone,
+        F: FnMut(&K, &V) -> B,
+    {
+        PMap {
+            len: self.len,
+            root: Arc::new(self.root.map(&mut f)),
+        }
+    }
+
+    /// Like [`map`](PMap::map), but stops at the first error.
+    ///
+    /// ```
+    /// use gazebo::persistent::PMap;
+    ///
+    /// let xs: PMap<_, _> = [("a", 1), ("b", -2)].into_iter().collect();
+    /// assert_eq!(
+    ///     xs.try_map(|k, v| if *v > 0 { Ok(*v) } else { Err(*k) })
+    ///         .err(),
+    ///     Some("b")
+    /// );
+    /// ```
+    pub fn try_map<B, E, F>(&self, mut f: F) -> Result<PMap<K, B>, E>
+    where
+        K: Clone,
+        F: FnMut(&K, &V) -> Result<B, E>,
+    {
+        Ok(PMap {
+            len: self.len,
+            root: Arc::new(self.root.try_map(&mut f)?),
+        })
+    }
+}
+
+impl<K: Hash + Eq, V> PMap<K, V> {
+    /// Get the value associated with `key`.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_key_value(key).map(|(_, v)| v)
+    }
+
+    /// Get the entry associated with `key`.
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.root.get(hash_key(key), key)
+    }
+
+    /// Does the map contain `key`.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_key_value(key).is_some()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> PMap<K, V> {
+    /// Insert an entry, returning the previous value associated with `key`.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let hash = hash_key(&key);
+        let res = Arc::make_mut(&mut self.root).insert(0, hash, key, value);
+        if res.is_none() {
+            self.len += 1;
+        }
+        res
+    }
+
+    /// Remove the entry associated with `key`, returning its value.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = hash_key(key);
+        // Check first, so we don't copy shared nodes if there's nothing to remove.
+        self.root.get(hash, key)?;
+        let res = Arc::make_mut(&mut self.root).remove(0, hash, key);
+        if res.is_some() {
+            self.len -= 1;
+        }
+        res
+    }
+
+    /// Get a mutable reference to the value associated with `key`.
+    ///
+    /// Any nodes on the path to the entry which are shared with other maps are copied.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = hash_key(key);
+        // Check first, so we don't copy shared nodes if there's nothing to return.
+        self.root.get(hash, key)?;
+        Arc::make_mut(&mut self.root).get_mut(hash, key)
+    }
+}
+
+impl<K, V> Clone for PMap<K, V> {
+    fn clone(&self) -> Self {
+        PMap {
+            len: self.len,
+            root: self.root.dupe(),
+        }
+    }
+}
+
+impl<K, V> Dupe for PMap<K, V> {}
+
+impl<K, V> Default for PMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq + Borrow<Q>, Q: Hash + Eq + ?Sized, V> Index<&Q> for PMap<K, V> {
+    type Output = V;
+
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("key not found in `PMap`")
+    }
+}
+
+impl<K: Debug, V: Debug> Debug for PMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Hash + Eq, V: PartialEq> PartialEq for PMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl<K: Hash + Eq, V: Eq> Eq for PMap<K, V> {}
+
+impl<K: Hash, V: Hash> Hash for PMap<K, V> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Equal maps may iterate in different orders (within collision buckets), so
+        // combine the hashes of the entries with an order-independent sum.
+        let sum = self.iter().fold(0u64, |sum, entry| {
+            let mut hasher = KeyHasher::default();
+            entry.hash(&mut hasher);
+            sum.wrapping_add(hasher.finish())
+        });
+        self.len.hash(state);
+        sum.hash(state);
+    }
+}
+
+#[cfg(feature = "strong_hash")]
+impl<K: strong_hash::StrongHash, V: strong_hash::StrongHash> strong_hash::StrongHash
+    for PMap<K, V>
+{
+    fn strong_hash<H: Hasher>(&self, state: &mut H) {
+        // Iteration order depends on `hash_key` and on insertion order within collision
+        // buckets, neither of which is stable, so hash the entries in a canonical order:
+        // record the bytes each entry feeds to the hasher, and sort them.
+        let mut entries: Vec<Vec<u8>> = self
+            .iter()
+            .map(|(k, v)| {
+                let mut bytes = RecordHasher(Vec::new());
+                k.strong_hash(&mut bytes);
+                v.strong_hash(&mut bytes);
+                bytes.0
+            })
+            .collect();
+        entries.sort_unstable();
+        self.len.strong_hash(state);
+        for bytes in entries {
+            bytes.len().strong_hash(state);
+            state.write(&bytes);
+        }
+    }
+}
+
+/// A `Hasher` that records the bytes it is fed.
+#[cfg(feature = "strong_hash")]
+struct RecordHasher(Vec<u8>);
+
+#[cfg(feature = "strong_hash")]
+impl Hasher for RecordHasher {
+    fn finish(&self) -> u64 {
+        // Only the recorded bytes are used.
+        0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> FromIterator<(K, V)> for PMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut res = PMap::new();
+        res.extend(iter);
+        res
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Extend<(K, V)> for PMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a PMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = PMapIter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the entries of a [`PMap`], created by [`PMap::iter`].
+pub struct PMapIter<'a, K, V> {
+    stack: Vec<slice::Iter<'a, Child<K, V>>>,
+    collision: slice::Iter<'a, (K, V)>,
+    len: usize,
+}
+
+impl<K, V> Clone for PMapIter<'_, K, V> {
+    fn clone(&self) -> Self {
+        PMapIter {
+            stack: self.stack.clone(),
+            collision: self.collision.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for PMapIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        loop {
+            if let Some((k, v)) = self.collision.next() {
+                self.len -= 1;
+                return Some((k, v));
+            }
+            match self.stack.last_mut()?.next() {
+                None => {
+                    self.stack.pop();
+                }
+                Some(Child::Entry(_, k, v)) => {
+                    self.len -= 1;
+                    return Some((k, v));
+                }
+                Some(Child::Collision(_, entries)) => self.collision = entries.iter(),
+                Some(Child::Node(n)) => self.stack.push(n.children.iter()),
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<K, V> ExactSizeIterator for PMapIter<'_, K, V> {}
+
+impl<K, V> FusedIterator for PMapIter<'_, K, V> {}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    // All keys collide, to exercise collision buckets.
+    #[derive(Clone, PartialEq, Eq, Debug)]
+    struct Collide(u32);
+
+    impl Hash for Collide {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            (self.0 % 3).hash(state);
+        }
+    }
+
+    #[cfg(feature = "strong_hash")]
+    impl strong_hash::StrongHash for Collide {
+        fn strong_hash<H: Hasher>(&self, state: &mut H) {
+            self.0.strong_hash(state);
+        }
+    }
+
+    #[test]
+    fn test_insert_remove() {
+        let mut xs = PMap::new();
+        let mut expect = HashMap::new();
+        for i in 0..3000u32 {
+            assert_eq!(xs.insert(i, i * 2), expect.insert(i, i * 2));
+        }
+        assert_eq!(xs.insert(7, 0), Some(14));
+        expect.insert(7, 0);
+        assert_eq!(xs.len(), expect.len());
+        assert_eq!(xs.iter().count(), expect.len());
+        for (k, v) in &expect {
+            assert_eq!(xs.get(k), Some(v));
+        }
+        for i in (0..3000u32).step_by(3) {
+            assert_eq!(xs.remove(&i), expect.remove(&i));
+        }
+        assert_eq!(xs.remove(&0), None);
+        assert_eq!(xs.len(), expect.len());
+        assert_eq!(
+            xs.iter().map(|(k, v)| (*k, *v)).collect::<HashMap<_, _>>(),
+            expect
+        );
+        for i in 0..3000u32 {
+            xs.remove(&i);
+        }
+        assert!(xs.is_empty());
+        assert!(xs.root.children.is_empty());
+    }
+
+    #[test]
+    fn test_collisions() {
+        let mut xs = PMap::new();
+        for i in 0..30 {
+            xs.insert(Collide(i), i);
+        }
+        assert_eq!(xs.len(), 30);
+        for i in 0..30 {
+            assert_eq!(xs.get(&Collide(i)), Some(&i));
+        }
+        *xs.get_mut(&Collide(4)).unwrap() = 40;
+        assert_eq!(xs.get(&Collide(4)), Some(&40));
+        for i in 0..29 {
+            assert_eq!(xs.remove(&Collide(i)), Some(if i == 4 { 40 } else { i }));
+        }
+        assert_eq!(xs.iter().collect::<Vec<_>>(), vec![(&Collide(29), &29)]);
+    }
+
+    #[test]
+    fn test_sharing() {
+        let xs: PMap<_, _> = (0..1000).map(|i| (i, Arc::new(i))).collect();
+        let mut ys = xs.clone();
+        *ys.get_mut(&5).unwrap() = Arc::new(0);
+        ys.remove(&6);
+        ys.insert(1000, Arc::new(1000));
+        assert_eq!(xs.get(&5).map(|x| **x), Some(5));
+        assert!(xs.contains_key(&6));
+        assert_eq!(xs.len(), 1000);
+        assert_eq!(ys.get(&5).map(|x| **x), Some(0));
+        assert!(!ys.contains_key(&6));
+        assert_eq!(ys.len(), 1000);
+        assert!(Arc::ptr_eq(&xs[&7], &ys[&7]));
+        assert_ne!(xs, ys);
+        ys.insert(5, Arc::new(5));
+        ys.insert(6, Arc::new(6));
+        ys.remove(&1000);
+        assert_eq!(xs, ys);
+    }
+
+    #[cfg(feature = "strong_hash")]
+    #[test]
+    fn test_strong_hash() {
+        use strong_hash::StrongHash;
+
+        fn strong_hash<T: StrongHash>(x: &T) -> u64 {
+            let mut hasher = KeyHasher::default();
+            x.strong_hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let xs: PMap<_, _> = (0..100).map(|i| (Collide(i), i)).collect();
+        let ys: PMap<_, _> = (0..100).rev().map(|i| (Collide(i), i)).collect();
+        assert_eq!(xs, ys);
+        assert_ne!(xs.iter().collect::<Vec<_>>(), ys.iter().collect::<Vec<_>>());
+        assert_eq!(strong_hash(&xs), strong_hash(&ys));
+        assert_ne!(strong_hash(&xs), strong_hash(&ys.map(|_, v| v + 1)));
+
+        let xs: PMap<_, _> = (0..1000).map(|i| (i, i.to_string())).collect();
+        let ys: PMap<_, _> = (0..1000).rev().map(|i| (i, i.to_string())).collect();
+        assert_eq!(strong_hash(&xs), strong_hash(&ys));
+    }
+
+    #[test]
+    fn test_hash() {
+        fn hash<T: Hash>(x: &T) -> u64 {
+            let mut hasher = KeyHasher::default();
+            x.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let xs: PMap<_, _> = (0..100).map(|i| (Collide(i), i)).collect();
+        let ys: PMap<_, _> = (0..100).rev().map(|i| (Collide(i), i)).collect();
+        assert_ne!(xs.iter().collect::<Vec<_>>(), ys.iter().collect::<Vec<_>>());
+        assert_eq!(hash(&xs), hash(&ys));
+        assert_ne!(hash(&xs), hash(&ys.map(|_, v| v + 1)));
+        let mut zs = xs.clone();
+        zs.remove(&Collide(0));
+        assert_ne!(hash(&xs), hash(&zs));
+    }
+
+    #[test]
+    fn test_map() {
+        let xs: PMap<_, _> = (0..100).map(|i| (i, i)).collect();
+        let ys = xs.map(|k, v| k + v);
+        assert_eq!(ys, (0..100).map(|i| (i, i * 2)).collect());
+        assert_eq!(
+            xs.try_map(|k, v| if *k < 99 { Ok(*v) } else { Err(*k) }),
+            Err(99)
+        );
+    }
+}
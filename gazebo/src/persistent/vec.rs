@@ -0,0 +1,549 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+// A radix trie with a branching factor of `WIDTH`, with all elements stored in the leaves,
+// plus a separate "tail" leaf holding the last (up to `WIDTH`) elements. The tail makes
+// `push` and `pop` cheap, as most of them only touch the tail. The trie is always full
+// on the left, so the path to an element is given by the bits of its index.
+
+use std::fmt;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::iter::FusedIterator;
+use std::mem;
+use std::ops::Index;
+use std::slice;
+use std::sync::Arc;
+
+use dupe::Dupe;
+
+const BITS: usize = 5;
+const WIDTH: usize = 1 << BITS;
+const MASK: usize = WIDTH - 1;
+
+#[derive(Clone)]
+enum Node<T> {
+    Branch(Vec<Arc<Node<T>>>),
+    Leaf(Vec<T>),
+}
+
+impl<T> Node<T> {
+    fn branch_mut(&mut self) -> &mut Vec<Arc<Node<T>>> {
+        match self {
+            Node::Branch(children) => children,
+            Node::Leaf(_) => unreachable!("expected a branch node"),
+        }
+    }
+
+    fn map<B>(&self, f: &mut impl FnMut(&T) -> B) -> Node<B> {
+        match self {
+            Node::Branch(children) => {
+                Node::Branch(children.iter().map(|c| Arc::new(c.map(f))).collect())
+            }
+            Node::Leaf(xs) => Node::Leaf(xs.iter().map(f).collect()),
+        }
+    }
+
+    fn try_map<B, E>(&self, f: &mut impl FnMut(&T) -> Result<B, E>) -> Result<Node<B>, E> {
+        match self {
+            Node::Branch(children) => Ok(Node::Branch(
+                children
+                    .iter()
+                    .map(|c| Ok(Arc::new(c.try_map(f)?)))
+                    .collect::<Result<_, E>>()?,
+            )),
+            Node::Leaf(xs) => Ok(Node::Leaf(xs.iter().map(f).collect::<Result<_, E>>()?)),
+        }
+    }
+}
+
+/// A persistent vector, with O(1) `clone` and O(log n) `push`, `pop`, `get` and `get_mut`.
+///
+/// Clones share structure, and modifying a shared vector only copies the nodes on the path to
+/// the modified element. Modifying operations therefore require `T: Clone`, as elements in those
+/// nodes are cloned, so `T` should usually be [`Dupe`].
+///
+/// ```
+/// use gazebo::persistent::PVec;
+///
+/// let mut xs = PVec::new();
+/// xs.push("a");
+/// xs.push("b");
+/// let mut ys = xs.clone();
+/// ys.set(0, "c");
+/// assert_eq!(xs.iter().collect::<Vec<_>>(), vec![&"a", &"b"]);
+/// assert_eq!(ys.iter().collect::<Vec<_>>(), vec![&"c", &"b"]);
+/// ```
+pub struct PVec<T> {
+    len: usize,
+    // The level of the root node, in bits. Children of a node at level `BITS` are leaves.
+    shift: usize,
+    root: Arc<Node<T>>,
+    // Non-empty unless the vector is empty.
+    tail: Arc<Vec<T>>,
+}
+
+impl<T> PVec<T> {
+    /// Create a new empty vector.
+    pub fn new() -> Self {
+        PVec {
+            len: 0,
+            shift: BITS,
+            root: Arc::new(Node::Branch(Vec::new())),
+            tail: Arc::new(Vec::new()),
+        }
+    }
+
+    /// The number of elements in the vector.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Is the vector empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn tail_offset(&self) -> usize {
+        self.len - self.tail.len()
+    }
+
+    /// The leaf containing `index`, which must be in bounds.
+    fn leaf(&self, index: usize) -> &[T] {
+        if index >= self.tail_offset() {
+            return &self.tail;
+        }
+        let mut node = &*self.root;
+        let mut level = self.shift;
+        loop {
+            match node {
+                Node::Branch(children) => {
+                    node = &children[(index >> level) & MASK];
+                    level -= BITS;
+                }
+                Node::Leaf(xs) => return xs,
+            }
+        }
+    }
+
+    /// Get the element at `index`, or `None` if out of bounds.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index < self.len {
+            Some(&self.leaf(index)[index & MASK])
+        } else {
+            None
+        }
+    }
+
+    /// The first element, or `None` if empty.
+    pub fn first(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    /// The last element, or `None` if empty.
+    pub fn last(&self) -> Option<&T> {
+        self.tail.last()
+    }
+
+    /// Iterate over the elements in order.
+    pub fn iter(&self) -> PVecIter<'_, T> {
+        PVecIter {
+            vec: self,
+            index: 0,
+            leaf: [].iter(),
+        }
+    }
+
+    /// Create a new vector by applying `f` to each element, preserving the structure
+    /// of `self` rather than building the vector one element at a time.
+    ///
+    /// ```
+    /// use gazebo::persistent::PVec;
+    ///
+    /// let xs: PVec<_> = (1..4).collect();
+    /// assert_eq!(xs.map(|x| x * x), (1..4).map(|x| x * x).collect());
+    /// ```
+    pub fn map<B, F>(&self, mut f: F) -> PVec<B>
+    where
+        F: FnMut(&T) -> B,
+    {
+        PVec {
+            len: self.len,
+            shift: self.shift,
+            root: Arc::new(self.root.map(&mut f)),
+            tail: Arc::new(self.tail.iter().map(f).collect()),
+        }
+    }
+
+    /// Like [`map`](PVec::map), but stops at the first error.
+    ///
+    /// ```
+    /// use gazebo::persistent::PVec;
+    ///
+    /// let xs: PVec<i32> = (1..4).collect();
+    /// assert_eq!(
+    ///     xs.try_map(|x| if *x > 0 { Ok(x * x) } else { Err(*x) }),
+    ///     Ok((1..4).map(|x| x * x).collect())
+    /// );
+    /// assert_eq!(
+    ///     xs.try_map(|x| if *x < 2 { Ok(*x) } else { Err(*x) }),
+    ///     Err(2)
+    /// );
+    /// ```
+    pub fn try_map<B, E, F>(&self, mut f: F) -> Result<PVec<B>, E>
+    where
+        F: FnMut(&T) -> Result<B, E>,
+    {
+        Ok(PVec {
+            len: self.len,
+            shift: self.shift,
+            root: Arc::new(self.root.try_map(&mut f)?),
+            tail: Arc::new(self.tail.iter().map(f).collect::<Result<_, E>>()?),
+        })
+    }
+}
+
+impl<T: Clone> PVec<T> {
+    /// Append an element to the end of the vector.
+    pub fn push(&mut self, value: T) {
+        if self.tail.len() < WIDTH {
+            Arc::make_mut(&mut self.tail).push(value);
+            self.len += 1;
+            return;
+        }
+
+        // The tail is full, so move it into the trie and start a new tail.
+        let full = mem::replace(&mut self.tail, Arc::new(vec![value]));
+        let leaf = Arc::new(Node::Leaf(Arc::unwrap_or_clone(full)));
+        if self.len > 1 << (self.shift + BITS) {
+            // The trie is full, add a level above the root.
+            let path = new_path(self.shift, leaf);
+            self.root = Arc::new(Node::Branch(vec![self.root.dupe(), path]));
+            self.shift += BITS;
+        } else {
+            push_leaf(&mut self.root, self.shift, self.len - 1, leaf);
+        }
+        self.len += 1;
+    }
+
+    /// Remove the last element, or `None` if empty.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        let tail = Arc::make_mut(&mut self.tail);
+        let value = tail.pop();
+        if tail.is_empty() && self.len != 0 {
+            // Keep the tail non-empty by moving the last leaf of the trie into it.
+            let leaf = pop_leaf(&mut self.root, self.shift);
+            match Arc::unwrap_or_clone(leaf) {
+                Node::Leaf(xs) => self.tail = Arc::new(xs),
+                Node::Branch(_) => unreachable!("expected a leaf node"),
+            }
+            if self.shift > BITS
+                && let Node::Branch(children) = &*self.root
+                && children.len() == 1
+            {
+                self.root = children[0].dupe();
+                self.shift -= BITS;
+            }
+        }
+        value
+    }
+
+    /// Get a mutable reference to the element at `index`, or `None` if out of bounds.
+    ///
+    /// Any nodes on the path to the element which are shared with other vectors are copied.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index >= self.len {
+            return None;
+        }
+        let tail_offset = self.tail_offset();
+        if index >= tail_offset {
+            return Some(&mut Arc::make_mut(&mut self.tail)[index - tail_offset]);
+        }
+        let mut node = Arc::make_mut(&mut self.root);
+        let mut level = self.shift;
+        loop {
+            match node {
+                Node::Branch(children) => {
+                    node = Arc::make_mut(&mut children[(index >> level) & MASK]);
+                    level -= BITS;
+                }
+                Node::Leaf(xs) => return Some(&mut xs[index & MASK]),
+            }
+        }
+    }
+
+    /// Replace the element at `index`, returning the old value.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set(&mut self, index: usize, value: T) -> T {
+        let len = self.len;
+        match self.get_mut(index) {
+            Some(x) => mem::replace(x, value),
+            None => panic!("index {index} out of bounds for `PVec` of length {len}"),
+        }
+    }
+}
+
+/// A chain of branches of height `level`, ending in `leaf`.
+fn new_path<T>(level: usize, leaf: Arc<Node<T>>) -> Arc<Node<T>> {
+    if level == 0 {
+        leaf
+    } else {
+        Arc::new(Node::Branch(vec![new_path(level - BITS, leaf)]))
+    }
+}
+
+/// Add `leaf`, whose last element is at `index`, as the rightmost leaf under `node`.
+fn push_leaf<T: Clone>(node: &mut Arc<Node<T>>, level: usize, index: usize, leaf: Arc<Node<T>>) {
+    let children = Arc::make_mut(node).branch_mut();
+    if level == BITS {
+        children.push(leaf);
+        return;
+    }
+    let sub = (index >> level) & MASK;
+    if sub < children.len() {
+        push_leaf(&mut children[sub], level - BITS, index, leaf);
+    } else {
+        children.push(new_path(level - BITS, leaf));
+    }
+}
+
+/// Remove the rightmost leaf under `node`, along with any branches left empty.
+fn pop_leaf<T: Clone>(node: &mut Arc<Node<T>>, level: usize) -> Arc<Node<T>> {
+    let children = Arc::make_mut(node).branch_mut();
+    if level == BITS {
+        return children.pop().expect("branch nodes are never empty");
+    }
+    let last = children.last_mut().expect("branch nodes are never empty");
+    let leaf = pop_leaf(last, level - BITS);
+    if matches!(&**last, Node::Branch(c) if c.is_empty()) {
+        children.pop();
+    }
+    leaf
+}
+
+impl<T> Clone for PVec<T> {
+    fn clone(&self) -> Self {
+        PVec {
+            len: self.len,
+            shift: self.shift,
+            root: self.root.dupe(),
+            tail: self.tail.dupe(),
+        }
+    }
+}
+
+impl<T> Dupe for PVec<T> {}
+
+impl<T> Default for PVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Index<usize> for PVec<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        match self.get(index) {
+            Some(x) => x,
+            None => panic!(
+                "index {index} out of bounds for `PVec` of length {}",
+                self.len
+            ),
+        }
+    }
+}
+
+impl<T: Debug> Debug for PVec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for PVec<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for PVec<T> {}
+
+impl<T: Hash> Hash for PVec<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len.hash(state);
+        for x in self.iter() {
+            x.hash(state);
+        }
+    }
+}
+
+#[cfg(feature = "strong_hash")]
+impl<T: strong_hash::StrongHash> strong_hash::StrongHash for PVec<T> {
+    fn strong_hash<H: Hasher>(&self, state: &mut H) {
+        self.len.strong_hash(state);
+        for x in self.iter() {
+            x.strong_hash(state);
+        }
+    }
+}
+
+impl<T: Clone> FromIterator<T> for PVec<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut res = PVec::new();
+        res.extend(iter);
+        res
+    }
+}
+
+impl<T: Clone> Extend<T> for PVec<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.push(x);
+        }
+    }
+}
+
+impl<T: Clone> From<Vec<T>> for PVec<T> {
+    fn from(xs: Vec<T>) -> Self {
+        xs.into_iter().collect()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a PVec<T> {
+    type Item = &'a T;
+    type IntoIter = PVecIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the elements of a [`PVec`], created by [`PVec::iter`].
+pub struct PVecIter<'a, T> {
+    vec: &'a PVec<T>,
+    // The index of the start of the next leaf.
+    index: usize,
+    leaf: slice::Iter<'a, T>,
+}
+
+impl<T> Clone for PVecIter<'_, T> {
+    fn clone(&self) -> Self {
+        PVecIter {
+            vec: self.vec,
+            index: self.index,
+            leaf: self.leaf.clone(),
+        }
+    }
+}
+
+impl<'a, T> Iterator for PVecIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if let Some(x) = self.leaf.next() {
+            return Some(x);
+        }
+        if self.index >= self.vec.len {
+            return None;
+        }
+        let leaf = self.vec.leaf(self.index);
+        self.index += leaf.len();
+        self.leaf = leaf.iter();
+        self.leaf.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.vec.len - self.index + self.leaf.len();
+        (len, Some(len))
+    }
+}
+
+impl<T> ExactSizeIterator for PVecIter<'_, T> {}
+
+impl<T> FusedIterator for PVecIter<'_, T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_pop() {
+        let mut xs = PVec::new();
+        let mut expect = Vec::new();
+        for i in 0..5000 {
+            xs.push(i);
+            expect.push(i);
+            if i % 97 == 0 {
+                assert_eq!(xs.iter().copied().collect::<Vec<_>>(), expect);
+            }
+        }
+        assert_eq!(xs.len(), 5000);
+        assert_eq!(xs[1234], 1234);
+        assert_eq!(xs.get(5000), None);
+        while let Some(x) = xs.pop() {
+            assert_eq!(Some(x), expect.pop());
+            assert_eq!(xs.len(), expect.len());
+            assert_eq!(xs.last(), expect.last());
+        }
+        assert!(xs.is_empty());
+        assert_eq!(xs.shift, BITS);
+    }
+
+    #[test]
+    fn test_sharing() {
+        let xs: PVec<_> = (0..1000).map(Arc::new).collect();
+        let mut ys = xs.clone();
+        assert!(Arc::ptr_eq(&xs.root, &ys.root));
+        *ys.get_mut(500).unwrap() = Arc::new(0);
+        ys.set(999, Arc::new(1));
+        ys.push(Arc::new(1000));
+        assert_eq!(*xs[500], 500);
+        assert_eq!(*xs[999], 999);
+        assert_eq!(xs.len(), 1000);
+        assert_eq!(*ys[500], 0);
+        assert_eq!(*ys[999], 1);
+        assert_eq!(ys.len(), 1001);
+        // Untouched elements are shared, not copied.
+        assert!(Arc::ptr_eq(&xs[0], &ys[0]));
+        let Node::Branch(xs_children) = &*xs.root else {
+            unreachable!()
+        };
+        let Node::Branch(ys_children) = &*ys.root else {
+            unreachable!()
+        };
+        assert!(Arc::ptr_eq(&xs_children[0], &ys_children[0]));
+        assert!(!Arc::ptr_eq(&xs_children[15], &ys_children[15]));
+    }
+
+    #[test]
+    fn test_map() {
+        let xs: PVec<_> = (0..100).collect();
+        let ys = xs.map(|x| x.to_string());
+        assert_eq!(ys, (0..100).map(|x| x.to_string()).collect());
+        assert_eq!(xs.try_map(|x| Ok::<_, ()>(x + 1)), Ok((1..101).collect()));
+        assert_eq!(
+            xs.try_map(|x| if *x < 50 { Ok(*x) } else { Err(*x) }),
+            Err(50)
+        );
+        assert_eq!(xs.map(|_| ()).len(), 100);
+    }
+
+    #[test]
+    fn test_debug() {
+        let xs: PVec<_> = vec![1, 2, 3].into();
+        assert_eq!(format!("{xs:?}"), "[1, 2, 3]");
+    }
+}